@@ -0,0 +1,297 @@
+//! Summarizing many videos, playlists and channels in one run, with a report of what failed.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use tracing::{info, warn};
+
+use crate::error::{self, Error};
+use crate::output::{self, OutputFormat};
+use crate::pipeline::VideoSummarizer;
+use crate::playlist::{BatchInput, PlaylistResolver};
+use crate::video_ref::VideoRef;
+
+/// outcome for one video of a batch, or for a playlist or channel whose videos
+/// couldn't be listed
+#[derive(Debug, Serialize)]
+pub struct BatchItem {
+    /// the url as given, video, playlist or channel
+    pub url: String,
+    /// absent when the url isn't a video
+    pub video_id: Option<String>,
+    /// whether the result was written
+    pub ok: bool,
+    /// file the result was written to
+    pub output: Option<String>,
+    /// why the video failed, with its causes
+    pub error: Option<String>,
+    /// category of the failure, e.g. "rate_limited"; absent for failures outside
+    /// the known categories
+    pub error_kind: Option<&'static str>,
+    /// exit code of the failure, 1 for failures outside the known categories;
+    /// absent for successes
+    pub exit_code: Option<u8>,
+    /// running the video again later may succeed
+    pub retryable: bool,
+    /// how long to wait before that, if the server said
+    pub retry_after_secs: Option<f64>,
+}
+
+/// what a batch did, written to `report.json` in the output directory
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    /// number of items
+    pub total: usize,
+    /// videos whose result was written
+    pub succeeded: usize,
+    /// videos, playlists and channels that failed
+    pub failed: usize,
+    /// playlists and channels that couldn't be listed, then one per video in input order
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchReport {
+    /// short text summary listing the failures, for the end of a run
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Processed {} items: {} succeeded, {} failed",
+            self.total, self.succeeded, self.failed
+        );
+        for item in self.items.iter().filter(|item| !item.ok) {
+            out.push_str(&format!(
+                "\n  FAILED {}: {}",
+                item.url,
+                item.error.as_deref().unwrap_or("unknown error")
+            ));
+        }
+        out
+    }
+}
+
+/// how `run_batch` runs
+pub struct BatchOptions {
+    /// videos summarized at the same time
+    pub jobs: usize,
+    /// where each result and the report are written
+    pub output_dir: PathBuf,
+    /// format of each result, which also decides its file extension
+    pub format: OutputFormat,
+}
+
+/// reads a url list: one per line, blank lines and # comments ignored
+pub fn read_url_file(path: &str) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read URL list {}", path))?;
+
+    Ok(content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// the videos a batch's inputs stand for
+#[derive(Debug)]
+pub struct ExpandedInputs {
+    /// video urls in input order, each video once
+    pub urls: Vec<String>,
+    /// playlists and channels whose videos couldn't be listed
+    pub failed: Vec<BatchItem>,
+}
+
+/// turns playlists and channels into their video urls, keeping video urls as they
+/// are. a video listed more than once is only kept the first time. a playlist or
+/// channel that can't be listed is recorded as failed, and the other inputs still run
+pub fn expand_inputs(inputs: &[String], resolver: &PlaylistResolver) -> ExpandedInputs {
+    let mut urls: Vec<String> = Vec::new();
+    let mut failed: Vec<BatchItem> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    let mut add = |url: String| {
+        let key = VideoRef::parse(&url).map(|video| video.id).unwrap_or_else(|_| url.clone());
+        if seen.insert(key) {
+            urls.push(url);
+        }
+    };
+
+    for input in inputs {
+        let ids = match BatchInput::classify(input) {
+            BatchInput::Video(url) => {
+                add(url);
+                continue;
+            }
+            BatchInput::Playlist(id) => resolver.playlist_video_ids(&id),
+            BatchInput::Channel(path) => resolver.channel_video_ids(&path),
+        };
+        let ids = match ids {
+            Ok(ids) => ids,
+            Err(e) => {
+                warn!("Failed to list the videos of {}: {:#}", input, e);
+                failed.push(BatchItem::failed(input, None, &e));
+                continue;
+            }
+        };
+
+        info!("Found {} videos in {}", ids.len(), input);
+        for id in ids {
+            add(format!("https://www.youtube.com/watch?v={}", id));
+        }
+    }
+
+    ExpandedInputs { urls, failed }
+}
+
+/// summarizes every url with at most `jobs` running at once, writing one file per
+/// video into the output directory. the report lists the inputs that failed to
+/// expand first, then the videos in input order
+pub fn run_batch(summarizer: &VideoSummarizer, inputs: ExpandedInputs, options: &BatchOptions) -> Result<BatchReport> {
+    let urls = &inputs.urls;
+    fs::create_dir_all(&options.output_dir)
+        .context(format!("Failed to create output directory {}", options.output_dir.display()))?;
+
+    let next = AtomicUsize::new(0);
+    let items_by_url: Mutex<Vec<Option<BatchItem>>> = Mutex::new(urls.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.clamp(1, urls.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(url) = urls.get(i) else { break };
+
+                info!("[{}/{}] {}", i + 1, urls.len(), url);
+                let item = process_one(summarizer, url, options);
+                items_by_url.lock().unwrap()[i] = Some(item);
+            });
+        }
+    });
+
+    let mut items = inputs.failed;
+    items.extend(items_by_url.into_inner().unwrap().into_iter().flatten());
+    let succeeded = items.iter().filter(|item| item.ok).count();
+
+    let report = BatchReport {
+        total: items.len(),
+        succeeded,
+        failed: items.len() - succeeded,
+        items,
+    };
+
+    let report_path = options.output_dir.join("report.json");
+    fs::write(&report_path, serde_json::to_string_pretty(&report)?)
+        .context(format!("Failed to write {}", report_path.display()))?;
+
+    Ok(report)
+}
+
+fn process_one(summarizer: &VideoSummarizer, url: &str, options: &BatchOptions) -> BatchItem {
+    let video_id = VideoRef::parse(url).ok().map(|video| video.id);
+
+    let result = summarizer.process_video(url)
+        .and_then(|summary| output::render_summary(&summary, options.format))
+        .and_then(|rendered| {
+            let name = video_id.as_deref().unwrap_or("unknown");
+            let path = output_path(&options.output_dir, name, options.format);
+            fs::write(&path, rendered)
+                .context(format!("Failed to write {}", path.display()))?;
+            Ok(path)
+        });
+
+    match result {
+        Ok(path) => BatchItem {
+            url: url.to_string(),
+            video_id,
+            ok: true,
+            output: Some(path.display().to_string()),
+            error: None,
+            error_kind: None,
+            exit_code: None,
+            retryable: false,
+            retry_after_secs: None,
+        },
+        Err(e) => BatchItem::failed(url, video_id, &e),
+    }
+}
+
+impl BatchItem {
+    fn failed(url: &str, video_id: Option<String>, error: &anyhow::Error) -> Self {
+        let kind = error::classify(error);
+        BatchItem {
+            url: url.to_string(),
+            video_id,
+            ok: false,
+            output: None,
+            error: Some(format!("{:#}", error)),
+            error_kind: kind.map(Error::kind),
+            exit_code: Some(error::exit_code(error)),
+            retryable: kind.is_some_and(Error::is_transient),
+            retry_after_secs: kind.and_then(Error::retry_after).map(|wait| wait.as_secs_f64()),
+        }
+    }
+}
+
+fn output_path(dir: &Path, video_id: &str, format: OutputFormat) -> PathBuf {
+    dir.join(format!("{}.{}", video_id, format.extension()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractive::TextRankSummarizer;
+    use crate::transcript::YouTubeTranscriptSource;
+    use std::net::TcpListener;
+
+    // a resolver whose every request fails, pointed at a port nothing listens on
+    fn unreachable_resolver() -> PlaylistResolver {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        PlaylistResolver::with_base_url(&format!("http://127.0.0.1:{}", port))
+    }
+
+    #[test]
+    fn unlistable_playlists_are_recorded_and_the_rest_kept() {
+        let inputs: Vec<String> = [
+            "https://www.youtube.com/playlist?list=PLabc",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://www.youtube.com/@somechannel/videos",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "9bZkp7q19f0",
+        ]
+        .iter()
+        .map(|input| input.to_string())
+        .collect();
+
+        let expanded = expand_inputs(&inputs, &unreachable_resolver());
+
+        assert_eq!(expanded.urls, ["https://youtu.be/dQw4w9WgXcQ", "9bZkp7q19f0"]);
+        let failed: Vec<&str> = expanded.failed.iter().map(|item| item.url.as_str()).collect();
+        assert_eq!(failed, [inputs[0].as_str(), inputs[2].as_str()]);
+        for item in &expanded.failed {
+            assert!(!item.ok);
+            assert_eq!(item.video_id, None);
+            assert_eq!(item.exit_code, Some(1));
+            assert!(item.error.is_some());
+        }
+    }
+
+    #[test]
+    fn report_lists_failed_inputs_before_videos() {
+        let summarizer = VideoSummarizer::new(Box::new(TextRankSummarizer::new()), Box::new(YouTubeTranscriptSource::new()));
+        let dir = std::env::temp_dir().join(format!("batch-report-{}", std::process::id()));
+        let options = BatchOptions { jobs: 2, output_dir: dir.clone(), format: OutputFormat::Text };
+
+        let inputs = ExpandedInputs {
+            urls: vec!["https://example.com/not-a-video".to_string()],
+            failed: vec![BatchItem::failed("https://www.youtube.com/playlist?list=PLabc", None, &anyhow::anyhow!("boom"))],
+        };
+        let report = run_batch(&summarizer, inputs, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((report.total, report.succeeded, report.failed), (2, 0, 2));
+        assert_eq!(report.items[0].url, "https://www.youtube.com/playlist?list=PLabc");
+        assert_eq!(report.items[1].error_kind, Some("invalid_url"));
+    }
+}
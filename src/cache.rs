@@ -0,0 +1,95 @@
+//! On-disk cache of transcripts and chunk summaries.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::dirs;
+use crate::transcript::Transcript;
+
+/// on-disk store of transcripts and chunk summaries, so interrupted runs pick up
+/// where they stopped and repeated runs don't spend API quota again.
+/// layout: `<dir>/<video id>/transcript-<key>.json` and `<dir>/<video id>/chunks/<key>.txt`
+pub struct SummaryCache {
+    dir: PathBuf,
+}
+
+impl SummaryCache {
+    /// a cache in `dir`, which is created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SummaryCache { dir: dir.into() }
+    }
+
+    /// $XDG_CACHE_HOME/youtube_summarizer, falling back to ~/.cache/youtube_summarizer
+    pub fn default_dir() -> PathBuf {
+        dirs::app_dir_or_relative("XDG_CACHE_HOME", ".cache")
+    }
+
+    /// `fingerprint` identifies the track preferences the transcript was fetched with
+    pub fn get_transcript(&self, video_id: &str, fingerprint: &str) -> Option<Transcript> {
+        let content = fs::read_to_string(self.transcript_path(video_id, fingerprint)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// stores a transcript for `get_transcript`
+    pub fn put_transcript(&self, video_id: &str, fingerprint: &str, transcript: &Transcript) -> Result<()> {
+        write_atomic(&self.transcript_path(video_id, fingerprint), &serde_json::to_string(transcript)?)
+    }
+
+    /// `fingerprint` identifies the model and generation parameters that produced the summary
+    pub fn get_chunk(&self, video_id: &str, fingerprint: &str, chunk: &str) -> Option<String> {
+        fs::read_to_string(self.chunk_path(video_id, fingerprint, chunk)).ok()
+    }
+
+    /// stores the summary of `chunk` for `get_chunk`
+    pub fn put_chunk(&self, video_id: &str, fingerprint: &str, chunk: &str, summary: &str) -> Result<()> {
+        write_atomic(&self.chunk_path(video_id, fingerprint, chunk), summary)
+    }
+
+    fn video_dir(&self, video_id: &str) -> PathBuf {
+        self.dir.join(video_id)
+    }
+
+    fn transcript_path(&self, video_id: &str, fingerprint: &str) -> PathBuf {
+        let key = format!("{:016x}", fnv1a(fingerprint.as_bytes()));
+        self.video_dir(video_id).join(format!("transcript-{}.json", key))
+    }
+
+    fn chunk_path(&self, video_id: &str, fingerprint: &str, chunk: &str) -> PathBuf {
+        let key = format!("{:016x}{:016x}", fnv1a(fingerprint.as_bytes()), fnv1a(chunk.as_bytes()));
+        self.video_dir(video_id).join("chunks").join(format!("{}.txt", key))
+    }
+}
+
+// write to a temporary file first so an interrupted run never leaves a truncated
+// entry, and readers never see a half-written one. every write gets its own
+// temporary file, so threads writing the same entry don't trip over each other
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context(format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    let tmp = path.with_extension(format!(
+        "tmp{}-{}",
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, content)
+        .context(format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+        .context(format!("Failed to write {}", path.display()))
+}
+
+// stable across rust versions and platforms, unlike DefaultHasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,240 @@
+//! Chapters: finding them in video descriptions and splitting transcripts along them.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::transcript::TranscriptSegment;
+
+/// chapter start as listed in a video description, e.g. "12:34 Results"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChapterMarker {
+    /// seconds from the start of the video
+    pub start: f64,
+    /// title as written in the description
+    pub title: String,
+}
+
+/// summary of one part of the video, anchored to where it starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterSummary {
+    /// absent for fixed-length parts of videos without chapters
+    pub title: Option<String>,
+    /// seconds from the start of the video
+    pub start: f64,
+    /// seconds from the start of the video
+    pub end: f64,
+    /// link to the video at `start`
+    pub url: String,
+    /// summary of the transcript between `start` and `end`
+    pub summary: String,
+}
+
+/// a run of consecutive transcript segments belonging to one chapter
+pub struct Chapter<'a> {
+    /// absent for fixed-length parts of videos without chapters
+    pub title: Option<String>,
+    /// seconds from the start of the video
+    pub start: f64,
+    /// seconds from the start of the video
+    pub end: f64,
+    /// the transcript between `start` and `end`
+    pub segments: &'a [TranscriptSegment],
+}
+
+/// parses "0:00 Intro" style timestamps from a description. youtube only treats
+/// them as chapters when the list starts at 0:00 and has at least two entries,
+/// and so do we
+pub fn parse_chapter_markers(description: &str) -> Vec<ChapterMarker> {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let re = MARKER.get_or_init(|| {
+        Regex::new(r"^\s*[\[(]?((?:\d{1,2}:)?\d{1,2}:\d{2})[\])]?\s*[-–—:|]?\s*(.+?)\s*$").unwrap()
+    });
+
+    let mut markers: Vec<ChapterMarker> = Vec::new();
+    for line in description.lines() {
+        let Some(cap) = re.captures(line) else { continue };
+        let Some(start) = parse_timestamp(&cap[1]) else { continue };
+
+        // timestamps must increase, anything else is not a chapter list
+        if markers.last().is_some_and(|last| start <= last.start) {
+            continue;
+        }
+
+        markers.push(ChapterMarker {
+            start,
+            title: cap[2].to_string(),
+        });
+    }
+
+    if markers.len() < 2 || markers[0].start != 0.0 {
+        return Vec::new();
+    }
+
+    markers
+}
+
+/// splits segments at the chapter markers, or into fixed windows when there are none
+pub fn group_segments<'a>(
+    segments: &'a [TranscriptSegment],
+    markers: &[ChapterMarker],
+    window_secs: f64,
+) -> Vec<Chapter<'a>> {
+    let Some(last) = segments.last() else { return Vec::new() };
+    let video_end = last.start + last.duration;
+
+    let boundaries: Vec<(f64, Option<String>)> = if markers.is_empty() {
+        let window = window_secs.max(1.0);
+        let count = (video_end / window).ceil().max(1.0) as usize;
+        (0..count).map(|i| (i as f64 * window, None)).collect()
+    } else {
+        markers.iter().map(|m| (m.start, Some(m.title.clone()))).collect()
+    };
+
+    let mut chapters = Vec::new();
+    let mut rest = segments;
+
+    for (i, (start, title)) in boundaries.iter().enumerate() {
+        let next_start = boundaries.get(i + 1).map(|(s, _)| *s);
+        let len = match next_start {
+            Some(next) => rest.iter().take_while(|s| s.start < next).count(),
+            None => rest.len(),
+        };
+
+        let (taken, remaining) = rest.split_at(len);
+        rest = remaining;
+
+        if taken.is_empty() {
+            continue;
+        }
+
+        chapters.push(Chapter {
+            title: title.clone(),
+            start: *start,
+            end: next_start.unwrap_or(video_end).min(video_end),
+            segments: taken,
+        });
+    }
+
+    chapters
+}
+
+/// deep link that starts playback at the given offset
+pub fn watch_url(video_id: &str, start: f64) -> String {
+    format!("https://www.youtube.com/watch?v={}&t={}s", video_id, start.floor() as u64)
+}
+
+/// formats seconds as m:ss or h:mm:ss, the way youtube displays them
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    let (h, m, s) = (total / 3600, total % 3600 / 60, total % 60);
+
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+fn parse_timestamp(text: &str) -> Option<f64> {
+    text.split(':')
+        .try_fold(0u64, |acc, part| part.parse::<u64>().ok().map(|n| acc * 60 + n))
+        .map(|secs| secs as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(start: f64, title: &str) -> ChapterMarker {
+        ChapterMarker { start, title: title.to_string() }
+    }
+
+    // five ten second segments, 0:00 to 0:50
+    fn segments() -> Vec<TranscriptSegment> {
+        (0..5)
+            .map(|i| TranscriptSegment { start: i as f64 * 10.0, duration: 10.0, text: format!("line {}", i) })
+            .collect()
+    }
+
+    fn summarize(chapters: &[Chapter]) -> Vec<(Option<String>, f64, f64, usize)> {
+        chapters.iter()
+            .map(|chapter| (chapter.title.clone(), chapter.start, chapter.end, chapter.segments.len()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_timestamp_lines_from_descriptions() {
+        let description = "\
+Thanks for watching!
+0:00 Intro
+1:30 - Setup
+[12:05] Results: part one
+1:02:03 | Outro
+Follow me at example.com";
+
+        assert_eq!(parse_chapter_markers(description), vec![
+            marker(0.0, "Intro"),
+            marker(90.0, "Setup"),
+            marker(725.0, "Results: part one"),
+            marker(3723.0, "Outro"),
+        ]);
+    }
+
+    #[test]
+    fn chapter_lists_start_at_zero_and_have_two_entries() {
+        assert!(parse_chapter_markers("0:30 Intro\n1:00 Main part").is_empty());
+        assert!(parse_chapter_markers("0:00 Intro").is_empty());
+        assert!(parse_chapter_markers("no chapters here").is_empty());
+    }
+
+    #[test]
+    fn timestamps_that_go_backwards_are_skipped() {
+        let markers = parse_chapter_markers("0:00 A\n2:00 B\n1:00 C\n2:00 D\n3:00 E");
+        let titles: Vec<&str> = markers.iter().map(|m| m.title.as_str()).collect();
+        assert_eq!(titles, ["A", "B", "E"]);
+    }
+
+    #[test]
+    fn groups_segments_by_chapter() {
+        let segments = segments();
+        let chapters = group_segments(&segments, &[marker(0.0, "A"), marker(25.0, "B")], 300.0);
+        assert_eq!(summarize(&chapters), vec![
+            (Some("A".to_string()), 0.0, 25.0, 3),
+            (Some("B".to_string()), 25.0, 50.0, 2),
+        ]);
+    }
+
+    #[test]
+    fn chapters_without_segments_are_skipped_and_ends_clamped() {
+        let segments = segments();
+        let markers = [marker(0.0, "A"), marker(12.0, "B"), marker(15.0, "C"), marker(30.0, "D"), marker(100.0, "E")];
+        let chapters = group_segments(&segments, &markers, 300.0);
+        assert_eq!(summarize(&chapters), vec![
+            (Some("A".to_string()), 0.0, 12.0, 2),
+            (Some("C".to_string()), 15.0, 30.0, 1),
+            (Some("D".to_string()), 30.0, 50.0, 2),
+        ]);
+    }
+
+    #[test]
+    fn videos_without_chapters_are_split_into_windows() {
+        let segments = segments();
+        assert_eq!(summarize(&group_segments(&segments, &[], 20.0)), vec![
+            (None, 0.0, 20.0, 2),
+            (None, 20.0, 40.0, 2),
+            (None, 40.0, 50.0, 1),
+        ]);
+        assert_eq!(summarize(&group_segments(&segments, &[], 600.0)), vec![(None, 0.0, 50.0, 5)]);
+        assert!(group_segments(&[], &[], 20.0).is_empty());
+    }
+
+    #[test]
+    fn formats_timestamps_like_youtube() {
+        let cases = [(0.0, "0:00"), (59.9, "0:59"), (61.0, "1:01"), (600.0, "10:00"), (3600.0, "1:00:00"), (3723.5, "1:02:03"), (-5.0, "0:00")];
+        for (seconds, expected) in cases {
+            assert_eq!(format_timestamp(seconds), expected, "{}", seconds);
+        }
+        assert_eq!(watch_url("dQw4w9WgXcQ", 90.7), "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=90s");
+    }
+}
@@ -0,0 +1,283 @@
+//! Splitting transcripts into sentences and model-sized chunks.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::transcript::{transcript_text, TranscriptSegment};
+
+// silence between caption lines that we treat as the end of a sentence
+const PAUSE_GAP_SECS: f64 = 0.8;
+// auto-captions rarely pause, so cap unpunctuated "sentences" at this many words
+const MAX_SENTENCE_WORDS: usize = 40;
+
+/// measures text the way a model's context limit does
+pub trait Tokenizer: Send + Sync {
+    /// number of tokens `text` takes up
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// approximates byte-pair encodings like BART's: short words are one token,
+/// longer ones split roughly every four characters, punctuation is separate.
+/// errs on the high side so chunks stay within the real limit
+pub struct ApproxBpeTokenizer;
+
+impl Tokenizer for ApproxBpeTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.split_whitespace()
+            .map(|word| {
+                let punctuation = word.chars().filter(|c| c.is_ascii_punctuation()).count();
+                let letters = word.chars().count() - punctuation;
+                letters.div_ceil(4).max(1) + punctuation
+            })
+            .sum()
+    }
+}
+
+/// one token per word, for backends that bill or limit by words
+pub struct WordTokenizer;
+
+impl Tokenizer for WordTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+/// splits punctuated text into sentences
+pub fn split_sentences(text: &str) -> Vec<String> {
+    static SENTENCE_END: OnceLock<Regex> = OnceLock::new();
+    let re = SENTENCE_END.get_or_init(|| Regex::new(r#"[.!?]+["')\]]*\s+"#).unwrap());
+
+    let mut sentences = Vec::new();
+    let mut last = 0;
+    for m in re.find_iter(text) {
+        push_sentence(&mut sentences, &text[last..m.end()]);
+        last = m.end();
+    }
+    push_sentence(&mut sentences, &text[last..]);
+
+    sentences
+}
+
+/// builds sentences from caption segments. punctuated transcripts are split on
+/// punctuation; auto-generated ones without it are split on pauses in speech
+pub fn sentences_from_segments(segments: &[TranscriptSegment]) -> Vec<String> {
+    let text = transcript_text(segments);
+    if is_punctuated(&text) {
+        return split_sentences(&text);
+    }
+
+    let mut sentences = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut words = 0;
+
+    for (i, segment) in segments.iter().enumerate() {
+        current.push(&segment.text);
+        words += segment.text.split_whitespace().count();
+
+        let pause = segments.get(i + 1)
+            .map(|next| next.start - (segment.start + segment.duration))
+            .unwrap_or(0.0);
+
+        if pause >= PAUSE_GAP_SECS || words >= MAX_SENTENCE_WORDS {
+            push_sentence(&mut sentences, &current.join(" "));
+            current.clear();
+            words = 0;
+        }
+    }
+    push_sentence(&mut sentences, &current.join(" "));
+
+    sentences
+}
+
+/// packs whole sentences into chunks of at most `max_tokens`, repeating up to
+/// `overlap_tokens` worth of trailing sentences at the start of the next chunk
+pub struct Chunker<'a> {
+    /// how chunk sizes are measured
+    pub tokenizer: &'a dyn Tokenizer,
+    /// largest chunk, at least 1
+    pub max_tokens: usize,
+    /// context repeated from the previous chunk, 0 for none
+    pub overlap_tokens: usize,
+}
+
+impl Chunker<'_> {
+    /// chunks of the sentences in order. a sentence longer than `max_tokens` is
+    /// split between words
+    pub fn chunk(&self, sentences: &[String]) -> Vec<String> {
+        let max_tokens = self.max_tokens.max(1);
+
+        // pieces are sentences, with any sentence too long for a chunk split on words
+        let pieces: Vec<(String, usize)> = sentences.iter()
+            .flat_map(|s| self.split_long(s, max_tokens))
+            .map(|s| {
+                let tokens = self.tokenizer.count_tokens(&s);
+                (s, tokens)
+            })
+            .collect();
+
+        let mut chunks = Vec::new();
+        let mut current: Vec<&(String, usize)> = Vec::new();
+        let mut current_tokens = 0;
+
+        for piece in &pieces {
+            if current_tokens + piece.1 > max_tokens && !current.is_empty() {
+                chunks.push(join_pieces(&current));
+
+                // carry trailing sentences over, but never so many that nothing new fits
+                let mut overlap = Vec::new();
+                let mut overlap_tokens = 0;
+                for prev in current.iter().rev() {
+                    if overlap_tokens + prev.1 > self.overlap_tokens
+                        || overlap_tokens + prev.1 + piece.1 > max_tokens
+                    {
+                        break;
+                    }
+                    overlap_tokens += prev.1;
+                    overlap.insert(0, *prev);
+                }
+
+                current = overlap;
+                current_tokens = overlap_tokens;
+            }
+            current.push(piece);
+            current_tokens += piece.1;
+        }
+
+        if !current.is_empty() {
+            chunks.push(join_pieces(&current));
+        }
+
+        chunks
+    }
+
+    fn split_long(&self, sentence: &str, max_tokens: usize) -> Vec<String> {
+        if self.tokenizer.count_tokens(sentence) <= max_tokens {
+            return vec![sentence.to_string()];
+        }
+
+        let mut parts = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        for word in sentence.split_whitespace() {
+            current.push(word);
+            if self.tokenizer.count_tokens(&current.join(" ")) > max_tokens && current.len() > 1 {
+                current.pop();
+                parts.push(current.join(" "));
+                current = vec![word];
+            }
+        }
+        if !current.is_empty() {
+            parts.push(current.join(" "));
+        }
+
+        parts
+    }
+}
+
+fn join_pieces(pieces: &[&(String, usize)]) -> String {
+    pieces.iter()
+        .map(|(text, _)| text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn push_sentence(sentences: &mut Vec<String>, text: &str) {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        sentences.push(text);
+    }
+}
+
+// auto-generated captions have next to no sentence punctuation
+fn is_punctuated(text: &str) -> bool {
+    let words = text.split_whitespace().count();
+    let stops = text.matches(['.', '!', '?']).count();
+    words > 0 && stops * 50 >= words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, start: f64, duration: f64) -> TranscriptSegment {
+        TranscriptSegment { start, duration, text: text.to_string() }
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn chunk(sentences: &[&str], max_tokens: usize, overlap_tokens: usize) -> Vec<String> {
+        Chunker { tokenizer: &WordTokenizer, max_tokens, overlap_tokens }.chunk(&strings(sentences))
+    }
+
+    #[test]
+    fn splits_on_sentence_punctuation() {
+        assert_eq!(
+            split_sentences("Hello there.  How are you?! He said \"fine.\" Then  left"),
+            ["Hello there.", "How are you?!", "He said \"fine.\"", "Then left"]
+        );
+        assert_eq!(split_sentences("version 2.0 is out"), ["version 2.0 is out"]);
+        assert!(split_sentences("  ").is_empty());
+    }
+
+    #[test]
+    fn packs_whole_sentences_into_chunks() {
+        assert_eq!(chunk(&["a b c", "d e", "f g h"], 6, 0), ["a b c d e", "f g h"]);
+        assert_eq!(chunk(&["a b c", "d e", "f g h"], 100, 0), ["a b c d e f g h"]);
+        assert!(chunk(&[], 6, 0).is_empty());
+    }
+
+    #[test]
+    fn repeats_trailing_sentences_as_overlap() {
+        assert_eq!(chunk(&["a b", "c d", "e f", "g h"], 6, 2), ["a b c d e f", "e f g h"]);
+        assert_eq!(chunk(&["a b", "c d", "e f", "g h"], 6, 4), ["a b c d e f", "c d e f g h"]);
+    }
+
+    #[test]
+    fn overlap_never_crowds_out_the_next_sentence() {
+        // carrying "c d" over would leave no room for the four new words
+        assert_eq!(chunk(&["a b", "c d", "e f g h"], 4, 4), ["a b c d", "e f g h"]);
+    }
+
+    #[test]
+    fn splits_sentences_longer_than_a_chunk_on_words() {
+        let chunks = chunk(&["one two three four five six seven"], 3, 0);
+        assert_eq!(chunks, ["one two three", "four five six", "seven"]);
+    }
+
+    #[test]
+    fn unpunctuated_captions_split_on_pauses() {
+        let segments = [
+            segment("so today we", 0.0, 1.0),
+            segment("talk about rust", 1.0, 1.0),
+            segment("next up is cargo", 3.0, 1.0),
+        ];
+        assert_eq!(sentences_from_segments(&segments), ["so today we talk about rust", "next up is cargo"]);
+    }
+
+    #[test]
+    fn unpunctuated_captions_without_pauses_are_capped_in_length() {
+        let segments: Vec<TranscriptSegment> = (0..50).map(|i| segment("word", i as f64, 1.0)).collect();
+        let lengths: Vec<usize> = sentences_from_segments(&segments).iter()
+            .map(|sentence| sentence.split_whitespace().count())
+            .collect();
+        assert_eq!(lengths, [MAX_SENTENCE_WORDS, 50 - MAX_SENTENCE_WORDS]);
+    }
+
+    #[test]
+    fn punctuated_captions_ignore_pauses() {
+        let segments = [
+            segment("This is one", 0.0, 1.0),
+            segment("sentence. And another.", 5.0, 1.0),
+        ];
+        assert_eq!(sentences_from_segments(&segments), ["This is one sentence.", "And another."]);
+    }
+
+    #[test]
+    fn punctuated_means_a_stop_every_fifty_words() {
+        let words = |n: usize| vec!["word"; n].join(" ");
+        assert!(is_punctuated(&format!("{}.", words(50))));
+        assert!(!is_punctuated(&format!("{}.", words(51))));
+        assert!(!is_punctuated(""));
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
+
+use youtube_summarizer::OutputFormat;
+
+use crate::logger::LogFormat;
+
+pub const USAGE: &str = "\
+Usage: youtube_summarizer [OPTIONS] <COMMAND>
+
+Commands:
+  summarize <URL>...   Summarize one or more videos
+  transcript <URL>     Print the transcript of a video
+  batch [URL]...       Summarize many videos; URLs may be playlists or channels
+  serve                Run an HTTP server offering summaries as a REST API
+  help                 Print this message
+
+Run without a command to be prompted for a URL.
+
+Options:
+  -c, --config <PATH>       Config file, TOML or JSON [default: ./config.toml or ./config.json]
+  -p, --profile <NAME>      Config profile to use, e.g. work or local-llm
+  -b, --backend <NAME>      Summarizer: huggingface, openai or extractive (offline) [default: huggingface]
+  -m, --model <ID>          Model id, e.g. facebook/bart-large-cnn or llama3.1:8b
+      --endpoint <URL>      Inference Endpoint / TGI URL, or base URL of an OpenAI-compatible API
+      --prompt-file <PATH>  Prompt template for the openai backend, with a {text} placeholder
+  -l, --lang <CODES>        Transcript languages in order of preference, e.g. de,es,en [default: en]
+      --translate-to <LANG> Translate transcripts in other languages into LANG, e.g. en
+      --translator <NAME>   Who translates: youtube, huggingface or openai [default: youtube]
+  -f, --format <FORMAT>     Output format: text, json, markdown, srt or vtt [default: text]
+  -o, --output <PATH>       Write the result to a file instead of stdout
+      --chunk-size <N>      Chunk size in model tokens
+      --min-length <N>      Minimum length of each chunk summary in tokens
+      --max-length <N>      Maximum length of each chunk summary in tokens
+      --do-sample           Sample instead of decoding greedily
+      --no-do-sample        Decode greedily, even if the config or environment samples
+      --temperature <T>     Sampling temperature
+      --num-beams <N>       Beam search width
+      --chapters            Summarize each chapter separately
+      --workers <N>         Chunks of a video summarized at the same time [default: 4]
+      --rate-limit <N>      Requests per minute to each backend API [default: unlimited]
+      --cache-dir <DIR>     Where transcripts and chunk summaries are cached
+      --no-cache            Neither read nor write the cache
+  -q, --quiet               Only log warnings and errors
+  -v, --verbose             Log more detail; -vv for everything
+      --log-format <FORMAT> Log format on stderr: text or json [default: text]
+
+Batch options:
+  -i, --input <PATH>        File with one URL per line
+  -j, --jobs <N>            Videos processed at the same time, also for serve [default: 2]
+      --output-dir <DIR>    Directory for results and report.json [default: summaries]
+
+Serve options:
+      --listen <ADDR>       Address to listen on [default: 127.0.0.1:8000]
+      --jobs-dir <DIR>      Where jobs are stored [default: ~/.local/share/youtube_summarizer/jobs]
+  -h, --help                Print this message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Summarize { urls: Vec<String> },
+    Transcript { url: String },
+    Batch { urls: Vec<String> },
+    Serve,
+    // no command given: prompt for a url like the original tool did
+    Interactive,
+    Help,
+}
+
+// parsed command line; unset options fall back to the config file
+#[derive(Debug, Clone)]
+pub struct Cli {
+    pub command: Command,
+    pub config: Option<String>,
+    pub profile: Option<String>,
+    pub backend: Option<String>,
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+    pub prompt_file: Option<String>,
+    pub languages: Option<Vec<String>>,
+    pub translate_to: Option<String>,
+    pub translator: Option<String>,
+    pub format: OutputFormat,
+    pub output: Option<String>,
+    pub chunk_tokens: Option<usize>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub do_sample: Option<bool>,
+    pub temperature: Option<f64>,
+    pub num_beams: Option<u32>,
+    pub chapters: bool,
+    pub workers: Option<usize>,
+    pub rate_limit: Option<f64>,
+    pub cache_dir: Option<String>,
+    pub no_cache: bool,
+    pub quiet: bool,
+    pub verbose: u8,
+    pub log_format: LogFormat,
+    pub input: Option<String>,
+    pub jobs: usize,
+    pub output_dir: String,
+    pub listen: String,
+    pub jobs_dir: Option<String>,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = Cli {
+            command: Command::Interactive,
+            config: None,
+            profile: None,
+            backend: None,
+            model: None,
+            endpoint: None,
+            prompt_file: None,
+            languages: None,
+            translate_to: None,
+            translator: None,
+            format: OutputFormat::Text,
+            output: None,
+            chunk_tokens: None,
+            min_length: None,
+            max_length: None,
+            do_sample: None,
+            temperature: None,
+            num_beams: None,
+            chapters: false,
+            workers: None,
+            rate_limit: None,
+            cache_dir: None,
+            no_cache: false,
+            quiet: false,
+            verbose: 0,
+            log_format: LogFormat::Text,
+            input: None,
+            jobs: 2,
+            output_dir: "summaries".to_string(),
+            listen: "127.0.0.1:8000".to_string(),
+            jobs_dir: None,
+        };
+
+        let mut positional = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                positional.push(arg);
+                continue;
+            }
+            if arg == "--" {
+                positional.extend(args.by_ref());
+                break;
+            }
+
+            // accept both "--flag value" and "--flag=value"
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String> {
+                inline.clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("Missing value for {}", flag))
+            };
+
+            let is_switch = matches!(
+                flag.as_str(),
+                "-h" | "--help" | "--chapters" | "--no-cache" | "--do-sample" | "--no-do-sample" | "-q" | "--quiet" | "-v" | "-vv" | "--verbose"
+            );
+            if is_switch && inline.is_some() {
+                return Err(anyhow!("{} does not take a value", flag));
+            }
+
+            match flag.as_str() {
+                "-h" | "--help" => cli.command = Command::Help,
+                "-c" | "--config" => cli.config = Some(value()?),
+                "-p" | "--profile" => cli.profile = Some(value()?),
+                "-b" | "--backend" => cli.backend = Some(value()?),
+                "-m" | "--model" => cli.model = Some(value()?),
+                "--endpoint" => cli.endpoint = Some(value()?),
+                "--prompt-file" => cli.prompt_file = Some(value()?),
+                "-l" | "--lang" => cli.languages = Some(value()?.split(',').map(str::to_string).collect()),
+                "--translate-to" => cli.translate_to = Some(value()?),
+                "--translator" => cli.translator = Some(value()?),
+                "-f" | "--format" => cli.format = value()?.parse()?,
+                "-o" | "--output" => cli.output = Some(value()?),
+                "--chunk-size" => cli.chunk_tokens = Some(parse_number(&flag, &value()?)?),
+                "--min-length" => cli.min_length = Some(parse_number(&flag, &value()?)?),
+                "--max-length" => cli.max_length = Some(parse_number(&flag, &value()?)?),
+                "--do-sample" => cli.do_sample = Some(true),
+                "--no-do-sample" => cli.do_sample = Some(false),
+                "--temperature" => cli.temperature = Some(parse_value(&flag, &value()?)?),
+                "--num-beams" => cli.num_beams = Some(parse_value(&flag, &value()?)?),
+                "--chapters" => cli.chapters = true,
+                "--workers" => cli.workers = Some(parse_number(&flag, &value()?)?),
+                "--rate-limit" => cli.rate_limit = Some(parse_value(&flag, &value()?)?),
+                "--cache-dir" => cli.cache_dir = Some(value()?),
+                "--no-cache" => cli.no_cache = true,
+                "-q" | "--quiet" => cli.quiet = true,
+                "-v" | "--verbose" => cli.verbose += 1,
+                "-vv" => cli.verbose += 2,
+                "--log-format" => cli.log_format = value()?.parse()?,
+                "-i" | "--input" => cli.input = Some(value()?),
+                "-j" | "--jobs" => cli.jobs = parse_number(&flag, &value()?)?.max(1),
+                "--output-dir" => cli.output_dir = value()?,
+                "--listen" => cli.listen = value()?,
+                "--jobs-dir" => cli.jobs_dir = Some(value()?),
+                _ => return Err(anyhow!("Unknown option '{}'", arg)),
+            }
+        }
+
+        if cli.command == Command::Help {
+            return Ok(cli);
+        }
+
+        let mut positional = positional.into_iter();
+        cli.command = match positional.next().as_deref() {
+            None => Command::Interactive,
+            Some("help") => Command::Help,
+            Some("summarize") => {
+                let urls: Vec<String> = positional.collect();
+                if urls.is_empty() {
+                    return Err(anyhow!("summarize needs at least one URL"));
+                }
+                // subtitle files can't be joined into one
+                if urls.len() > 1 && cli.output.is_some() && matches!(cli.format, OutputFormat::Srt | OutputFormat::Vtt) {
+                    return Err(anyhow!("--output takes a single URL with --format srt or vtt, use batch for several"));
+                }
+                Command::Summarize { urls }
+            }
+            Some("batch") => {
+                let urls: Vec<String> = positional.collect();
+                if urls.is_empty() && cli.input.is_none() {
+                    return Err(anyhow!("batch needs URLs or --input"));
+                }
+                Command::Batch { urls }
+            }
+            Some("serve") => {
+                if let Some(extra) = positional.next() {
+                    return Err(anyhow!("Unexpected argument '{}'", extra));
+                }
+                Command::Serve
+            }
+            Some("transcript") => {
+                let url = positional.next().context("transcript needs a URL")?;
+                if let Some(extra) = positional.next() {
+                    return Err(anyhow!("Unexpected argument '{}'", extra));
+                }
+                Command::Transcript { url }
+            }
+            Some(other) => return Err(anyhow!("Unknown command '{}'", other)),
+        };
+
+        Ok(cli)
+    }
+}
+
+fn parse_number(flag: &str, value: &str) -> Result<usize> {
+    value.parse()
+        .with_context(|| format!("{} expects a positive number, got '{}'", flag, value))
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T> {
+    value.parse()
+        .map_err(|_| anyhow!("{} expects a number, got '{}'", flag, value))
+}
@@ -0,0 +1,466 @@
+//! Settings from the config file (TOML or JSON), its profiles and environment variables.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::dirs;
+use crate::secret::{self, Secret};
+use crate::toml;
+
+/// looked up in the user's config directory and in the current directory
+pub const CONFIG_NAMES: [&str; 2] = ["config.toml", "config.json"];
+
+/// struct to read config file for HF token and pipeline settings
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Hugging Face access token, only needed for the huggingface backend. prefer
+    /// HF_TOKEN, token_command or token_file over writing it into the config
+    pub token: Option<Secret>,
+    /// command printing the token, e.g. `pass show hf` or a keyring lookup
+    pub token_command: Option<String>,
+    /// file holding the token, which should be readable only by its owner
+    pub token_file: Option<String>,
+    /// "huggingface" (default), "openai" for OpenAI-compatible servers, or
+    /// "extractive" for offline TextRank summaries
+    pub backend: Option<String>,
+    /// model id for the huggingface and openai backends
+    pub model: Option<String>,
+    /// where the backend lives: a dedicated Inference Endpoint / TGI url for
+    /// huggingface, or the base url of an OpenAI-compatible api
+    pub endpoint: Option<String>,
+    /// bearer token for the OpenAI-compatible api, if it needs one
+    pub api_key: Option<Secret>,
+    /// system message for the openai backend
+    pub system_prompt: Option<String>,
+    /// user prompt template for the openai backend, with a {text} placeholder
+    pub prompt_template: Option<String>,
+    /// file to read `prompt_template` from
+    pub prompt_file: Option<String>,
+    /// transcript tokens per request for the openai backend
+    pub context_tokens: Option<usize>,
+    /// shortest chunk summary, in tokens (words for extractive)
+    pub min_length: Option<usize>,
+    /// longest chunk summary, in tokens (words for extractive)
+    pub max_length: Option<usize>,
+    /// sample instead of decoding greedily
+    pub do_sample: Option<bool>,
+    /// sampling temperature
+    pub temperature: Option<f64>,
+    /// beam search width for the huggingface backend
+    pub num_beams: Option<u32>,
+    /// caption languages to try in order, e.g. ["de", "es", "en"]; "*" takes any
+    pub languages: Option<Vec<String>>,
+    /// language to translate other-language transcripts into before summarizing
+    pub translate_to: Option<String>,
+    /// who translates: "youtube" (default, its machine translated captions),
+    /// "huggingface" or "openai"
+    pub translator: Option<String>,
+    /// model for the huggingface and openai translators
+    pub translation_model: Option<String>,
+    /// endpoint for the huggingface and openai translators
+    pub translation_endpoint: Option<String>,
+    /// summarize per chapter instead of the whole video
+    #[serde(default)]
+    pub chapters: bool,
+    /// chapter length in seconds when the video has no chapter markers
+    pub chapter_window: Option<f64>,
+    /// maximum number of reduce passes over the chunk summaries
+    pub reduce_depth: Option<usize>,
+    /// target length of the final summary in characters
+    pub summary_length: Option<usize>,
+    /// chunk size in model tokens, capped at the model's input limit
+    pub chunk_tokens: Option<usize>,
+    /// tokens of context repeated between consecutive chunks
+    pub chunk_overlap: Option<usize>,
+    /// how chunk length is measured: "bpe" (default) or "words"
+    pub tokenizer: Option<String>,
+    /// let the API hold requests while a cold model loads
+    #[serde(default)]
+    pub wait_for_model: bool,
+    /// retries for rate limits, model loading and server errors
+    pub max_retries: Option<u32>,
+    /// chunks of one video sent to the backend at the same time
+    pub workers: Option<usize>,
+    /// requests per minute to each backend API, unlimited when unset
+    pub rate_limit: Option<f64>,
+    /// requests that may go out at once before `rate_limit` kicks in
+    pub rate_limit_burst: Option<u32>,
+    /// where transcripts and chunk summaries are cached
+    pub cache_dir: Option<String>,
+    /// set to false to always fetch and summarize from scratch
+    pub cache: Option<bool>,
+    /// where `serve` keeps its jobs
+    pub jobs_dir: Option<String>,
+}
+
+impl Config {
+    /// merges the user config file and the project one (or `path`), applies the
+    /// selected profile on top, then environment overrides. without any file
+    /// every setting keeps its default
+    pub fn load(path: Option<&str>, profile: Option<&str>) -> Result<Config> {
+        let mut settings = Map::new();
+        let mut profiles: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+        let mut file_profile = None;
+
+        // later files win over earlier ones, key by key
+        for file in config_files(path)? {
+            let mut table = read_config(&file)?;
+            if table.contains_key("token") || table.contains_key("api_key") {
+                secret::warn_if_world_readable(&file);
+            }
+
+            if let Some(name) = table.remove("profile") {
+                let name = name.as_str()
+                    .with_context(|| format!("\"profile\" in {} must be a string", file.display()))?;
+                file_profile = Some(name.to_string());
+            }
+
+            if let Some(defined) = table.remove("profiles") {
+                let Value::Object(defined) = defined else {
+                    return Err(anyhow!("\"profiles\" in {} must be a table of profiles", file.display()));
+                };
+                for (name, values) in defined {
+                    let Value::Object(values) = values else {
+                        return Err(anyhow!("Profile '{}' in {} must be a table", name, file.display()));
+                    };
+                    validate(&values).with_context(|| format!("Invalid profile '{}' in {}", name, file.display()))?;
+                    profiles.entry(name).or_default().extend(values);
+                }
+            }
+
+            validate(&table).with_context(|| format!("Invalid config file {}", file.display()))?;
+            settings.extend(table);
+        }
+
+        let env_profile = env::var("SUMMARIZER_PROFILE").ok().filter(|name| !name.is_empty());
+        if let Some(name) = profile.map(str::to_string).or(env_profile).or(file_profile) {
+            let values = profiles.get(&name).with_context(|| {
+                let defined: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                if defined.is_empty() {
+                    format!("Unknown profile '{}': no profiles are defined", name)
+                } else {
+                    format!("Unknown profile '{}' (defined: {})", name, defined.join(", "))
+                }
+            })?;
+            settings.extend(values.clone());
+        }
+
+        let mut config: Config = serde_json::from_value(Value::Object(settings))
+            .context("Invalid config")?;
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// the Hugging Face token from, in order: HF_TOKEN, token_command, token_file
+    /// and the token in the config file
+    pub fn resolve_token(&self) -> Result<Option<Secret>> {
+        if let Some(token) = env::var("HF_TOKEN").ok().filter(|token| !token.is_empty()) {
+            return Ok(Some(Secret::new(token)));
+        }
+        if let Some(command) = &self.token_command {
+            return secret::from_command(command).map(Some);
+        }
+        if let Some(path) = &self.token_file {
+            return secret::from_file(Path::new(path)).map(Some);
+        }
+        Ok(self.token.clone())
+    }
+
+    // SUMMARIZER_* variables take precedence over the file; command line flags
+    // are applied on top of both by the caller
+    fn apply_env(&mut self) -> Result<()> {
+        env_override("OPENAI_API_KEY", &mut self.api_key)?;
+        env_override("SUMMARIZER_BACKEND", &mut self.backend)?;
+        env_override("SUMMARIZER_MODEL", &mut self.model)?;
+        env_override("SUMMARIZER_ENDPOINT", &mut self.endpoint)?;
+        env_override("SUMMARIZER_MIN_LENGTH", &mut self.min_length)?;
+        env_override("SUMMARIZER_MAX_LENGTH", &mut self.max_length)?;
+        env_override("SUMMARIZER_DO_SAMPLE", &mut self.do_sample)?;
+        env_override("SUMMARIZER_TEMPERATURE", &mut self.temperature)?;
+        env_override("SUMMARIZER_NUM_BEAMS", &mut self.num_beams)?;
+        env_override("SUMMARIZER_CACHE_DIR", &mut self.cache_dir)?;
+        Ok(())
+    }
+}
+
+/// $XDG_CONFIG_HOME/youtube_summarizer, falling back to ~/.config/youtube_summarizer
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+// the user's config file, then the one given on the command line or found in
+// the current directory
+fn config_files(path: Option<&str>) -> Result<Vec<PathBuf>> {
+    let find = |dir: &Path| CONFIG_NAMES.iter().map(|name| dir.join(name)).find(|file| file.is_file());
+
+    let mut files: Vec<PathBuf> = config_dir().and_then(|dir| find(&dir)).into_iter().collect();
+
+    let local = match path {
+        Some(path) if !Path::new(path).is_file() => {
+            return Err(anyhow!("Config file {} does not exist", path));
+        }
+        Some(path) => Some(PathBuf::from(path)),
+        None => find(Path::new(".")),
+    };
+    if let Some(local) = local {
+        let same = files.first()
+            .is_some_and(|user| user.canonicalize().ok() == local.canonicalize().ok());
+        if !same {
+            files.push(local);
+        }
+    }
+
+    Ok(files)
+}
+
+// parses a .toml file as TOML and anything else as JSON
+fn read_config(path: &Path) -> Result<Map<String, Value>> {
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to open config file at {}", path.display()))?;
+
+    let value = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::parse(&content),
+        _ => serde_json::from_str(&content).map_err(anyhow::Error::from),
+    }
+    .context(format!("Failed to parse config file {}", path.display()))?;
+
+    match value {
+        Value::Object(table) => Ok(table),
+        _ => Err(anyhow!("Config file {} must contain a table of settings", path.display())),
+    }
+}
+
+// checks key names and value types, so a typo is reported instead of ignored
+fn validate(table: &Map<String, Value>) -> Result<()> {
+    serde_json::from_value::<Config>(Value::Object(table.clone()))?;
+    Ok(())
+}
+
+fn env_override<T>(name: &str, target: &mut Option<T>) -> Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Ok(value) = env::var(name) else { return Ok(()) };
+    if value.is_empty() {
+        return Ok(());
+    }
+
+    let parsed = value.parse()
+        .map_err(|e| anyhow!("Invalid value '{}' for {}: {}", value, name, e))?;
+    *target = Some(parsed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // tests that read or change environment variables take turns
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn lock_env() -> std::sync::MutexGuard<'static, ()> {
+        ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // points the user config directory at `dir` and clears every override
+    fn isolate_env(dir: &Path) {
+        env::set_var("XDG_CONFIG_HOME", dir.join("xdg"));
+        for name in [
+            "SUMMARIZER_PROFILE", "OPENAI_API_KEY", "SUMMARIZER_BACKEND", "SUMMARIZER_MODEL", "SUMMARIZER_ENDPOINT",
+            "SUMMARIZER_MIN_LENGTH", "SUMMARIZER_MAX_LENGTH", "SUMMARIZER_DO_SAMPLE", "SUMMARIZER_TEMPERATURE",
+            "SUMMARIZER_NUM_BEAMS", "SUMMARIZER_CACHE_DIR",
+        ] {
+            env::remove_var(name);
+        }
+    }
+
+    fn write(path: &Path, content: &str) -> String {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        path.display().to_string()
+    }
+
+    fn load_error(path: &str, profile: Option<&str>) -> String {
+        format!("{:#}", Config::load(Some(path), profile).unwrap_err())
+    }
+
+    #[test]
+    fn debug_never_shows_tokens() {
+        let config = Config {
+            token: Some(Secret::new("hf_abcdef123456")),
+            api_key: Some(Secret::new("sk-abcdef123456")),
+            ..Config::default()
+        };
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("abcdef123456"), "{}", debug);
+        assert!(debug.contains("[redacted]"), "{}", debug);
+    }
+
+    #[test]
+    fn token_sources_in_order_of_precedence() {
+        let _env = lock_env();
+        let dir = temp_dir("token");
+        let token_file = dir.join("token");
+        fs::write(&token_file, "hf_from_file\n").unwrap();
+
+        let mut config = Config {
+            token: Some(Secret::new("hf_from_config")),
+            token_command: Some("echo hf_from_command".to_string()),
+            token_file: Some(token_file.display().to_string()),
+            ..Config::default()
+        };
+        let resolve = |config: &Config| config.resolve_token().unwrap().map(|token| token.expose().to_string());
+
+        env::set_var("HF_TOKEN", "hf_from_env");
+        let from_env = resolve(&config);
+        env::set_var("HF_TOKEN", "");
+        let empty_env = resolve(&config);
+        env::remove_var("HF_TOKEN");
+        let from_command = resolve(&config);
+        config.token_command = None;
+        let from_file = resolve(&config);
+        config.token_file = None;
+        let from_config = resolve(&config);
+        config.token = None;
+        let none = resolve(&config);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(from_env.as_deref(), Some("hf_from_env"));
+        assert_eq!(empty_env.as_deref(), Some("hf_from_command"));
+        assert_eq!(from_command.as_deref(), Some("hf_from_command"));
+        assert_eq!(from_file.as_deref(), Some("hf_from_file"));
+        assert_eq!(from_config.as_deref(), Some("hf_from_config"));
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn local_file_overrides_user_file_key_by_key() {
+        let _env = lock_env();
+        let dir = temp_dir("merge");
+        isolate_env(&dir);
+        write(&dir.join("xdg/youtube_summarizer/config.toml"), "backend = \"openai\"\nmodel = \"user-model\"\nmin_length = 10\n");
+        let local = write(&dir.join("project/config.json"), r#"{"model": "local-model", "max_length": 99}"#);
+
+        let merged = Config::load(Some(&local), None).unwrap();
+        let user_only = Config::load(None, None).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(merged.backend.as_deref(), Some("openai"));
+        assert_eq!(merged.model.as_deref(), Some("local-model"));
+        assert_eq!((merged.min_length, merged.max_length), (Some(10), Some(99)));
+        assert_eq!(user_only.model.as_deref(), Some("user-model"));
+    }
+
+    #[test]
+    fn profile_from_flag_then_environment_then_file() {
+        let _env = lock_env();
+        let dir = temp_dir("profiles");
+        isolate_env(&dir);
+        let path = write(&dir.join("config.toml"), "\
+model = \"base\"
+min_length = 10
+profile = \"work\"
+
+[profiles.work]
+model = \"work-model\"
+
+[profiles.local]
+model = \"local-model\"
+");
+        let model = |profile: Option<&str>| Config::load(Some(&path), profile).unwrap().model.unwrap();
+
+        let from_file = model(None);
+        env::set_var("SUMMARIZER_PROFILE", "local");
+        let from_env = model(None);
+        let from_flag = model(Some("work"));
+        env::remove_var("SUMMARIZER_PROFILE");
+        let kept = Config::load(Some(&path), Some("local")).unwrap().min_length;
+        let unknown = load_error(&path, Some("home"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(from_file, "work-model");
+        assert_eq!(from_env, "local-model");
+        assert_eq!(from_flag, "work-model");
+        assert_eq!(kept, Some(10));
+        assert!(unknown.contains("Unknown profile 'home' (defined: local, work)"), "{}", unknown);
+    }
+
+    #[test]
+    fn unknown_profile_without_profiles() {
+        let _env = lock_env();
+        let dir = temp_dir("no-profiles");
+        isolate_env(&dir);
+        let path = write(&dir.join("config.toml"), "model = \"base\"\n");
+
+        let error = load_error(&path, Some("work"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(error.contains("Unknown profile 'work': no profiles are defined"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_keys_name_the_file_or_profile() {
+        let _env = lock_env();
+        let dir = temp_dir("unknown-keys");
+        isolate_env(&dir);
+        let top_level = write(&dir.join("top.toml"), "modle = \"typo\"\n");
+        let in_profile = write(&dir.join("profile.toml"), "[profiles.work]\nmodle = \"typo\"\n");
+        let wrong_type = write(&dir.join("type.json"), r#"{"min_length": "ten"}"#);
+
+        let top_level_error = load_error(&top_level, None);
+        let in_profile_error = load_error(&in_profile, None);
+        let wrong_type_error = load_error(&wrong_type, None);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(top_level_error.contains(&format!("Invalid config file {}", top_level)), "{}", top_level_error);
+        assert!(top_level_error.contains("unknown field `modle`"), "{}", top_level_error);
+        assert!(in_profile_error.contains(&format!("Invalid profile 'work' in {}", in_profile)), "{}", in_profile_error);
+        assert!(in_profile_error.contains("unknown field `modle`"), "{}", in_profile_error);
+        assert!(wrong_type_error.contains(&format!("Invalid config file {}", wrong_type)), "{}", wrong_type_error);
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let _env = lock_env();
+        let dir = temp_dir("env");
+        isolate_env(&dir);
+        let path = write(&dir.join("config.toml"), "model = \"file-model\"\nmin_length = 10\ndo_sample = false\n");
+
+        env::set_var("SUMMARIZER_MODEL", "env-model");
+        env::set_var("SUMMARIZER_DO_SAMPLE", "true");
+        env::set_var("SUMMARIZER_MIN_LENGTH", "");
+        let config = Config::load(Some(&path), None).unwrap();
+        env::set_var("SUMMARIZER_MIN_LENGTH", "ten");
+        let error = load_error(&path, None);
+        isolate_env(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.model.as_deref(), Some("env-model"));
+        assert_eq!(config.do_sample, Some(true));
+        assert_eq!(config.min_length, Some(10));
+        assert!(error.contains("Invalid value 'ten' for SUMMARIZER_MIN_LENGTH"), "{}", error);
+    }
+
+    #[test]
+    fn missing_config_file_is_an_error() {
+        let error = load_error("/nonexistent/config.toml", None);
+        assert!(error.contains("Config file /nonexistent/config.toml does not exist"), "{}", error);
+    }
+}
@@ -0,0 +1,40 @@
+//! Where config, cache and data files go, following the XDG base directory spec.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+const APP_NAME: &str = "youtube_summarizer";
+
+/// `$<var>/youtube_summarizer`, falling back to `~/<home_subdir>/youtube_summarizer`
+/// when the variable is unset or empty. None when HOME isn't set either
+pub(crate) fn app_dir(var: &str, home_subdir: &str) -> Option<PathBuf> {
+    resolve(env::var_os(var), env::var_os("HOME"), home_subdir)
+}
+
+/// like `app_dir`, but relative to the current directory when there's no HOME
+pub(crate) fn app_dir_or_relative(var: &str, home_subdir: &str) -> PathBuf {
+    app_dir(var, home_subdir).unwrap_or_else(|| Path::new(home_subdir).join(APP_NAME))
+}
+
+fn resolve(xdg: Option<OsString>, home: Option<OsString>, home_subdir: &str) -> Option<PathBuf> {
+    xdg.filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| Path::new(&home).join(home_subdir)))
+        .map(|base| base.join(APP_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_the_xdg_variable_over_home() {
+        let dir = |xdg: Option<&str>, home: Option<&str>| resolve(xdg.map(OsString::from), home.map(OsString::from), ".local/share");
+
+        assert_eq!(dir(Some("/xdg"), Some("/home/me")), Some(PathBuf::from("/xdg/youtube_summarizer")));
+        assert_eq!(dir(Some(""), Some("/home/me")), Some(PathBuf::from("/home/me/.local/share/youtube_summarizer")));
+        assert_eq!(dir(None, Some("/home/me")), Some(PathBuf::from("/home/me/.local/share/youtube_summarizer")));
+        assert_eq!(dir(None, None), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! Failure categories worth telling apart, and the exit codes they map to.
+
+use std::fmt;
+use std::time::Duration;
+
+/// failures that callers handle differently: retry later, skip the video or alert
+/// someone. they travel inside anyhow errors; use `classify` to get them back out.
+/// anything else is reported as a general failure
+#[derive(Debug)]
+pub enum Error {
+    /// not a youtube video url or id
+    InvalidUrl(String),
+    /// the video can't be played (private, removed, region locked), has no track in
+    /// the requested languages, or its transcript is empty
+    TranscriptUnavailable {
+        /// the video asked for
+        video_id: String,
+        /// which of those it was, e.g. the tracks the video does have
+        reason: String,
+    },
+    /// the uploader turned captions off
+    TranscriptsDisabled {
+        /// the video asked for
+        video_id: String,
+    },
+    /// watching needs a signed-in, age-verified account
+    AgeRestricted {
+        /// the video asked for
+        video_id: String,
+    },
+    /// no token configured, or the api rejected it
+    Auth(String),
+    /// too many requests, even after retrying
+    RateLimited {
+        /// what the api said
+        message: String,
+        /// how long the api asked us to wait, if it did
+        retry_after: Option<Duration>,
+    },
+    /// the model is still being loaded onto the inference server
+    ModelLoading {
+        /// what the api said
+        message: String,
+        /// how long the api expects loading to take, if it said
+        estimated_time: Option<Duration>,
+    },
+    /// the api, or youtube, answered with something we couldn't read
+    ResponseParse(String),
+}
+
+impl Error {
+    /// process exit status for this kind of failure. 1 is any other failure and 2
+    /// invalid arguments, so these start at 3 and never change meaning
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::InvalidUrl(_) => 3,
+            Error::TranscriptUnavailable { .. } => 4,
+            Error::TranscriptsDisabled { .. } => 5,
+            Error::AgeRestricted { .. } => 6,
+            Error::Auth(_) => 7,
+            Error::RateLimited { .. } => 8,
+            Error::ModelLoading { .. } => 9,
+            Error::ResponseParse(_) => 10,
+        }
+    }
+
+    /// stable name of the category, e.g. for batch reports
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidUrl(_) => "invalid_url",
+            Error::TranscriptUnavailable { .. } => "transcript_unavailable",
+            Error::TranscriptsDisabled { .. } => "transcripts_disabled",
+            Error::AgeRestricted { .. } => "age_restricted",
+            Error::Auth(_) => "auth",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::ModelLoading { .. } => "model_loading",
+            Error::ResponseParse(_) => "response_parse",
+        }
+    }
+
+    /// whether running again later may succeed without changing anything
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::RateLimited { .. } | Error::ModelLoading { .. })
+    }
+
+    /// how long the server asked us to wait, when it said
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimited { retry_after, .. } => *retry_after,
+            Error::ModelLoading { estimated_time, .. } => *estimated_time,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidUrl(message) => write!(f, "{}", message),
+            Error::TranscriptUnavailable { video_id, reason } => {
+                write!(f, "No transcript available for video {}: {}", video_id, reason)
+            }
+            Error::TranscriptsDisabled { video_id } => write!(f, "Transcripts are disabled for video {}", video_id),
+            Error::AgeRestricted { video_id } => {
+                write!(f, "Video {} is age-restricted, its transcript needs a signed-in account", video_id)
+            }
+            Error::Auth(message) => write!(f, "Authentication failed: {}", message),
+            Error::RateLimited { message, .. } => write!(f, "Rate limit exceeded: {}", message),
+            Error::ModelLoading { message, .. } => write!(f, "Model is still loading: {}", message),
+            Error::ResponseParse(message) => write!(f, "Failed to parse API response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// the typed error behind any added context, if there is one
+pub fn classify(error: &anyhow::Error) -> Option<&Error> {
+    error.chain().find_map(|cause| cause.downcast_ref::<Error>())
+}
+
+/// exit status for a failed run: the category's code, or 1 for anything else
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    classify(error).map_or(1, Error::exit_code)
+}
+
+/// exit status when several videos failed: their shared code when they all failed
+/// the same way, otherwise 1. 0 when nothing failed
+pub fn combined_exit_code(codes: impl IntoIterator<Item = u8>) -> u8 {
+    let mut codes = codes.into_iter();
+    let Some(first) = codes.next() else { return 0 };
+    if codes.all(|code| code == first) {
+        first
+    } else {
+        1
+    }
+}
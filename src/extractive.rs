@@ -0,0 +1,288 @@
+//! An offline extractive summarizer, for when no model is available.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use crate::chunking;
+use crate::summarizer::{Summarizer, SummarizerLimits};
+
+const DAMPING: f64 = 0.85;
+const MAX_ITERATIONS: usize = 100;
+const CONVERGENCE: f64 = 1e-6;
+// sentences sharing more of their content words than this with one already chosen are skipped
+const MAX_REDUNDANCY: f64 = 0.7;
+// unpunctuated caption text is cut into pseudo-sentences of this many words
+const FALLBACK_SENTENCE_WORDS: usize = 20;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "because",
+    "been", "but", "by", "can", "could", "did", "do", "does", "for", "from", "get", "got", "had",
+    "has", "have", "he", "her", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its",
+    "just", "like", "me", "more", "my", "no", "not", "of", "on", "one", "or", "our", "out", "so",
+    "some", "that", "the", "their", "them", "then", "there", "these", "they", "this", "to", "um",
+    "uh", "up", "very", "was", "we", "were", "what", "when", "which", "who", "will", "with",
+    "would", "you", "your",
+];
+
+/// extractive summarizer: ranks sentences with TextRank and keeps the most central
+/// ones in their original order. runs in-process, no token or network needed
+pub struct TextRankSummarizer {
+    min_length: usize,
+    max_length: usize,
+}
+
+impl TextRankSummarizer {
+    /// summaries of 30 to 150 words
+    pub fn new() -> Self {
+        TextRankSummarizer {
+            min_length: 30,
+            max_length: 150,
+        }
+    }
+
+    /// bounds on the length of each summary, in words
+    pub fn with_length_bounds(mut self, min_length: usize, max_length: usize) -> Self {
+        self.min_length = min_length;
+        self.max_length = max_length;
+        self
+    }
+}
+
+impl Default for TextRankSummarizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Summarizer for TextRankSummarizer {
+    fn name(&self) -> &str {
+        "extractive:textrank"
+    }
+
+    fn version(&self) -> &str {
+        "1"
+    }
+
+    fn limits(&self) -> SummarizerLimits {
+        SummarizerLimits {
+            // ranking cost grows with the square of the sentence count, not the model's context
+            max_input_tokens: 4096,
+            min_summary_length: self.min_length,
+            max_summary_length: self.max_length,
+        }
+    }
+
+    fn summarize_chunk(&self, chunk: &str) -> Result<String> {
+        let sentences = sentences(chunk);
+        if sentences.is_empty() {
+            return Err(anyhow!("Nothing to summarize"));
+        }
+
+        let total_words: usize = sentences.iter().map(|s| word_count(s)).sum();
+        if total_words <= self.max_length {
+            return Ok(sentences.join(" "));
+        }
+
+        let content: Vec<HashSet<String>> = sentences.iter().map(|s| content_words(s)).collect();
+        let scores = text_rank(&content);
+        let mut ranked: Vec<usize> = (0..sentences.len()).collect();
+        ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+
+        // take the best sentences that fit, but always reach the minimum length.
+        // transcripts repeat themselves a lot, so near-duplicates of a chosen sentence are dropped
+        let mut chosen: Vec<usize> = Vec::new();
+        let mut words = 0;
+        for i in ranked {
+            let len = word_count(&sentences[i]);
+            if words + len > self.max_length && words >= self.min_length {
+                continue;
+            }
+            if chosen.iter().any(|j| overlap_ratio(&content[i], &content[*j]) > MAX_REDUNDANCY) {
+                continue;
+            }
+            chosen.push(i);
+            words += len;
+            if words >= self.max_length {
+                break;
+            }
+        }
+        chosen.sort_unstable();
+
+        Ok(chosen.iter()
+            .map(|i| sentences[*i].as_str())
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+}
+
+fn sentences(text: &str) -> Vec<String> {
+    let sentences = chunking::split_sentences(text);
+
+    // auto-captions have no punctuation: fall back to fixed-size word windows
+    let longest = sentences.iter().map(|s| word_count(s)).max().unwrap_or(0);
+    if longest <= FALLBACK_SENTENCE_WORDS * 3 {
+        return sentences;
+    }
+
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .chunks(FALLBACK_SENTENCE_WORDS)
+        .map(|words| words.join(" "))
+        .collect()
+}
+
+// scores sentences with PageRank over a graph weighted by the word-overlap
+// similarity from the original TextRank paper
+fn text_rank(words: &[HashSet<String>]) -> Vec<f64> {
+    let n = words.len();
+
+    let mut weights = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let similarity = similarity(&words[i], &words[j]);
+            weights[i][j] = similarity;
+            weights[j][i] = similarity;
+        }
+    }
+    let out_weight: Vec<f64> = weights.iter().map(|row| row.iter().sum()).collect();
+
+    let mut scores = vec![1.0 / n as f64; n];
+    for _ in 0..MAX_ITERATIONS {
+        let next: Vec<f64> = (0..n)
+            .map(|i| {
+                let incoming: f64 = (0..n)
+                    .filter(|j| out_weight[*j] > 0.0)
+                    .map(|j| weights[j][i] / out_weight[j] * scores[j])
+                    .sum();
+                (1.0 - DAMPING) / n as f64 + DAMPING * incoming
+            })
+            .collect();
+
+        let delta: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if delta < CONVERGENCE {
+            break;
+        }
+    }
+
+    scores
+}
+
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+    let overlap = a.intersection(b).count() as f64;
+    overlap / ((a.len() as f64).ln() + (b.len() as f64).ln())
+}
+
+// jaccard index of two sentences' content words
+fn overlap_ratio(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn content_words(sentence: &str) -> HashSet<String> {
+    static STOPWORD_SET: OnceLock<HashSet<&str>> = OnceLock::new();
+    let stopwords = STOPWORD_SET.get_or_init(|| STOPWORDS.iter().copied().collect());
+
+    sentence.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.trim_matches('\'').to_lowercase())
+        .filter(|word| word.len() > 1 && !stopwords.contains(word.as_str()))
+        .collect()
+}
+
+fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // distinct sentences of `words` words each, sharing "rust" so they are related
+    fn numbered_sentences(count: usize, words: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| {
+                let rest: Vec<String> = (1..words).map(|j| format!("w{}x{}", i, j)).collect();
+                format!("Rust {}.", rest.join(" "))
+            })
+            .collect()
+    }
+
+    fn positions(summary: &str, sentences: &[String]) -> Vec<usize> {
+        sentences.iter()
+            .enumerate()
+            .filter(|(_, sentence)| summary.contains(sentence.as_str()))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn short_input_is_returned_as_it_is() {
+        let summary = TextRankSummarizer::new().summarize_chunk("First point.  Second   point!").unwrap();
+        assert_eq!(summary, "First point. Second point!");
+        assert!(TextRankSummarizer::new().summarize_chunk("  ").is_err());
+    }
+
+    #[test]
+    fn stays_within_the_length_bounds() {
+        let text = numbered_sentences(30, 5).join(" ");
+        let summary = TextRankSummarizer::new().with_length_bounds(10, 20).summarize_chunk(&text).unwrap();
+
+        let words = word_count(&summary);
+        assert!((10..=20).contains(&words), "{} words: {}", words, summary);
+    }
+
+    #[test]
+    fn reaches_the_minimum_even_past_the_maximum() {
+        let text = numbered_sentences(10, 10).join(" ");
+        let summary = TextRankSummarizer::new().with_length_bounds(15, 12).summarize_chunk(&text).unwrap();
+        assert_eq!(word_count(&summary), 20, "{}", summary);
+    }
+
+    #[test]
+    fn keeps_the_original_sentence_order() {
+        let sentences = numbered_sentences(30, 5);
+        let summary = TextRankSummarizer::new().with_length_bounds(10, 40).summarize_chunk(&sentences.join(" ")).unwrap();
+
+        let chosen = positions(&summary, &sentences);
+        assert!(chosen.len() > 1, "{}", summary);
+        let expected: Vec<&str> = chosen.iter().map(|i| sentences[*i].as_str()).collect();
+        assert_eq!(summary, expected.join(" "));
+    }
+
+    #[test]
+    fn drops_near_duplicates_of_chosen_sentences() {
+        let repeated = "Rust makes memory safety practical for systems programmers.";
+        let mut sentences = numbered_sentences(20, 5);
+        for i in [2, 7, 12, 17] {
+            sentences.insert(i, repeated.to_string());
+        }
+
+        let summary = TextRankSummarizer::new().with_length_bounds(10, 40).summarize_chunk(&sentences.join(" ")).unwrap();
+        assert!(summary.matches(repeated).count() <= 1, "{}", summary);
+    }
+
+    #[test]
+    fn unpunctuated_text_falls_back_to_word_windows() {
+        let text: Vec<String> = (0..100).map(|i| format!("word{}", i)).collect();
+        let windows = sentences(&text.join(" "));
+        assert_eq!(windows.len(), 5);
+        assert!(windows.iter().all(|window| word_count(window) == FALLBACK_SENTENCE_WORDS));
+
+        let summary = TextRankSummarizer::new().with_length_bounds(10, 40).summarize_chunk(&text.join(" ")).unwrap();
+        assert_eq!(word_count(&summary), 40);
+    }
+
+    #[test]
+    fn content_words_skip_stopwords_and_case() {
+        let words = content_words("The Rust compiler, the rust COMPILER and I don't stop");
+        let expected: HashSet<String> = ["rust", "compiler", "don't", "stop"].iter().map(|w| w.to_string()).collect();
+        assert_eq!(words, expected);
+    }
+}
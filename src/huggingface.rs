@@ -0,0 +1,266 @@
+//! Summarizing and translating with the Hugging Face Inference API, Inference
+//! Endpoints and TGI servers.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::retry::RetryPolicy;
+use crate::secret::Secret;
+use crate::summarizer::{Summarizer, SummarizerLimits};
+use crate::translator::Translator;
+
+const DEFAULT_MODEL: &str = "facebook/bart-large-cnn";
+const INFERENCE_API_URL: &str = "https://api-inference.huggingface.co/models";
+
+/// translates from many languages into english
+pub const DEFAULT_TRANSLATION_MODEL: &str = "Helsinki-NLP/opus-mt-mul-en";
+
+// struct to store api response from HF transformer model.
+// TGI servers answer with generated_text instead
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    #[serde(alias = "generated_text")]
+    summary_text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslationResponse {
+    translation_text: String,
+}
+
+/// summarizer backed by the Hugging Face Inference API
+pub struct HuggingFaceSummarizer {
+    api_token: Secret,
+    api_url: String,
+    name: String,
+    min_length: usize,
+    max_length: usize,
+    do_sample: bool,
+    temperature: Option<f64>,
+    num_beams: Option<u32>,
+    // ask the API to hold the request until a cold model has loaded instead of returning 503
+    wait_for_model: bool,
+    retry: RetryPolicy,
+}
+
+impl HuggingFaceSummarizer {
+    /// summarizes with facebook/bart-large-cnn on the shared Inference API
+    pub fn new(api_token: Secret) -> Self {
+        HuggingFaceSummarizer {
+            api_token,
+            api_url: String::new(),
+            name: String::new(),
+            min_length: 30,
+            max_length: 150,
+            do_sample: false,
+            temperature: None,
+            num_beams: None,
+            wait_for_model: false,
+            retry: RetryPolicy::default(),
+        }
+        .with_model(DEFAULT_MODEL)
+    }
+
+    /// model id on the Hugging Face hub, e.g. "sshleifer/distilbart-cnn-12-6"
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.api_url = format!("{}/{}", INFERENCE_API_URL, model);
+        self.name = format!("huggingface:{}", model);
+        self
+    }
+
+    /// full url of a dedicated Inference Endpoint or self-hosted TGI server, which
+    /// serve a single model, used instead of the shared Inference API
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.api_url = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// sampling instead of greedy/beam decoding; temperature only applies when sampling
+    pub fn with_sampling(mut self, do_sample: bool, temperature: Option<f64>) -> Self {
+        self.do_sample = do_sample;
+        self.temperature = temperature;
+        self
+    }
+
+    /// beam search width, or the model's default
+    pub fn with_num_beams(mut self, num_beams: Option<u32>) -> Self {
+        self.num_beams = num_beams;
+        self
+    }
+
+    /// bounds on the length of each generated summary, in tokens
+    pub fn with_length_bounds(mut self, min_length: usize, max_length: usize) -> Self {
+        self.min_length = min_length;
+        self.max_length = max_length;
+        self
+    }
+
+    /// have the api hold the request while the model loads instead of answering 503
+    pub fn with_wait_for_model(mut self, wait_for_model: bool) -> Self {
+        self.wait_for_model = wait_for_model;
+        self
+    }
+
+    /// how rate limits and loading models are retried
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+impl Summarizer for HuggingFaceSummarizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        "main"
+    }
+
+    fn limits(&self) -> SummarizerLimits {
+        SummarizerLimits {
+            max_input_tokens: 1024,
+            min_summary_length: self.min_length,
+            max_summary_length: self.max_length,
+        }
+    }
+
+    fn summarize_chunk(&self, chunk: &str) -> Result<String> {
+        let limits = self.limits();
+
+        let mut parameters = ureq::json!({
+            "max_length": limits.max_summary_length,
+            "min_length": limits.min_summary_length,
+            "do_sample": self.do_sample
+        });
+        if let Some(temperature) = self.temperature {
+            parameters["temperature"] = ureq::json!(temperature);
+        }
+        if let Some(num_beams) = self.num_beams {
+            parameters["num_beams"] = ureq::json!(num_beams);
+        }
+
+        let body = ureq::json!({
+            "inputs": chunk,
+            "parameters": parameters,
+            "options": {
+                "wait_for_model": self.wait_for_model
+            }
+        });
+
+        let response = self.retry
+            .send_json(
+                || ureq::post(&self.api_url).set("Authorization", &format!("Bearer {}", self.api_token.expose())),
+                &body,
+            )
+            .context(format!("Failed to send request to API: {}", self.api_url))?;
+
+        let summary: Vec<ApiResponse> = response.into_json()
+            .map_err(|e| Error::ResponseParse(e.to_string()))?;
+
+        summary.into_iter()
+            .next()
+            .map(|s| s.summary_text)
+            .context("API response contained no summary")
+    }
+
+    fn fingerprint(&self) -> String {
+        format!(
+            "{}|{}|{:?}|{}|{:?}|{:?}",
+            self.name,
+            self.api_url,
+            self.limits(),
+            self.do_sample,
+            self.temperature,
+            self.num_beams
+        )
+    }
+}
+
+/// translator backed by a translation model (e.g. opus-mt) on the Inference API
+pub struct HuggingFaceTranslator {
+    api_token: Secret,
+    api_url: String,
+    name: String,
+    target_language: String,
+    wait_for_model: bool,
+    retry: RetryPolicy,
+}
+
+impl HuggingFaceTranslator {
+    /// `model` must translate into `target_language`; opus-mt models are named
+    /// after their language pair, e.g. Helsinki-NLP/opus-mt-de-en
+    pub fn new(api_token: Secret, model: &str, target_language: &str) -> Self {
+        HuggingFaceTranslator {
+            api_token,
+            api_url: format!("{}/{}", INFERENCE_API_URL, model),
+            name: format!("huggingface:{}", model),
+            target_language: target_language.to_string(),
+            wait_for_model: false,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// full url of a dedicated Inference Endpoint serving the model, used instead
+    /// of the shared Inference API
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.api_url = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// have the api hold the request while the model loads instead of answering 503
+    pub fn with_wait_for_model(mut self, wait_for_model: bool) -> Self {
+        self.wait_for_model = wait_for_model;
+        self
+    }
+
+    /// how rate limits and loading models are retried
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+impl Translator for HuggingFaceTranslator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn target_language(&self) -> &str {
+        &self.target_language
+    }
+
+    // marian models take 512 tokens
+    fn max_input_tokens(&self) -> usize {
+        512
+    }
+
+    fn translate_chunk(&self, chunk: &str) -> Result<String> {
+        let body = ureq::json!({
+            "inputs": chunk,
+            "options": {
+                "wait_for_model": self.wait_for_model
+            }
+        });
+
+        let response = self.retry
+            .send_json(
+                || ureq::post(&self.api_url).set("Authorization", &format!("Bearer {}", self.api_token.expose())),
+                &body,
+            )
+            .context(format!("Failed to send request to API: {}", self.api_url))?;
+
+        let translation: Vec<TranslationResponse> = response.into_json()
+            .map_err(|e| Error::ResponseParse(e.to_string()))?;
+
+        translation.into_iter()
+            .next()
+            .map(|t| t.translation_text)
+            .context("API response contained no translation")
+    }
+
+    fn fingerprint(&self) -> String {
+        format!("{}|{}|{}", self.name, self.api_url, self.target_language)
+    }
+}
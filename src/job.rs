@@ -0,0 +1,258 @@
+//! Summarization jobs stored on disk, for the server and other long-running callers.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache::write_atomic;
+use crate::dirs;
+use crate::error;
+use crate::pipeline::{Progress, Summary, VideoSummarizer};
+
+/// where a job is at. serialized as `{"status": "summarizing", "done": 3, "total": 9}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobState {
+    /// waiting for a worker
+    Queued,
+    /// downloading the captions
+    FetchingTranscript,
+    /// summarizing the transcript chunks
+    Summarizing {
+        /// chunks summarized so far
+        done: usize,
+        /// chunks in the transcript
+        total: usize,
+    },
+    /// merging the chunk summaries
+    Reducing {
+        /// the current pass, counting from 1
+        pass: usize,
+        /// the most passes that will be made
+        max_passes: usize,
+    },
+    /// the summary is ready
+    Done,
+    /// the job gave up
+    Failed {
+        /// what went wrong, with its causes
+        error: String,
+        /// category of the failure, e.g. "rate_limited", if it has one
+        error_kind: Option<String>,
+    },
+}
+
+impl JobState {
+    /// done or failed, so the state won't change anymore
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed { .. })
+    }
+}
+
+impl From<&Progress> for JobState {
+    fn from(progress: &Progress) -> Self {
+        match progress {
+            Progress::FetchingTranscript { .. } => JobState::FetchingTranscript,
+            Progress::Summarizing { done, total } => JobState::Summarizing { done: *done, total: *total },
+            Progress::Reducing { pass, max_passes } => JobState::Reducing { pass: *pass, max_passes: *max_passes },
+        }
+    }
+}
+
+/// one video to summarize and how far it got
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Job {
+    /// random hex id, also the job's file name
+    pub id: String,
+    /// the video as it was submitted
+    pub url: String,
+    /// where the job is at
+    #[serde(flatten)]
+    pub state: JobState,
+    /// when the job was created, in seconds since the unix epoch
+    pub created_at: u64,
+    /// when the state last changed, in seconds since the unix epoch
+    pub updated_at: u64,
+    /// the result, once the job is done
+    pub summary: Option<Summary>,
+}
+
+/// jobs kept as one json file each, so they survive restarts and other processes
+/// can follow their progress. layout: `<dir>/<job id>.json`
+pub struct JobStore {
+    dir: PathBuf,
+    next_id: AtomicU64,
+    // held while a job is read, changed and written back, so concurrent
+    // updates (progress from several chunk workers) don't undo each other
+    updates: Mutex<()>,
+}
+
+impl JobStore {
+    /// a store in `dir`, which is created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        JobStore {
+            dir: dir.into(),
+            next_id: AtomicU64::new(0),
+            updates: Mutex::new(()),
+        }
+    }
+
+    /// $XDG_DATA_HOME/youtube_summarizer/jobs, falling back to ~/.local/share/youtube_summarizer/jobs
+    pub fn default_dir() -> PathBuf {
+        dirs::app_dir_or_relative("XDG_DATA_HOME", ".local/share").join("jobs")
+    }
+
+    /// stores a new queued job for `url`
+    pub fn create(&self, url: &str) -> Result<Job> {
+        let now = now_secs();
+        let job = Job {
+            id: self.new_id(),
+            url: url.to_string(),
+            state: JobState::Queued,
+            created_at: now,
+            updated_at: now,
+            summary: None,
+        };
+        self.save(&job)?;
+        Ok(job)
+    }
+
+    /// the job with `id`, if there is one
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let content = fs::read_to_string(self.path(id)?).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// writes `job` as it is. use `update` to change a stored job, so concurrent
+    /// changes don't overwrite each other
+    pub fn save(&self, job: &Job) -> Result<()> {
+        let path = self.path(&job.id)
+            .with_context(|| format!("Invalid job id '{}'", job.id))?;
+        write_atomic(&path, &serde_json::to_string_pretty(job)?)
+    }
+
+    /// moves a job to `state`. progress reported out of order by parallel
+    /// workers never moves the chunk count backwards
+    pub fn set_state(&self, id: &str, state: JobState) -> Result<()> {
+        self.update(id, |job| {
+            if let (JobState::Summarizing { done: stored, total: stored_total }, JobState::Summarizing { done, total }) = (&job.state, &state) {
+                if stored_total == total && done < stored {
+                    return;
+                }
+            }
+            job.state = state;
+        })
+    }
+
+    /// applies `change` to the stored job and writes it back
+    pub fn update(&self, id: &str, change: impl FnOnce(&mut Job)) -> Result<()> {
+        let _guard = self.updates.lock().unwrap();
+        let mut job = self.get(id).with_context(|| format!("No job with id {}", id))?;
+        change(&mut job);
+        job.updated_at = now_secs();
+        self.save(&job)
+    }
+
+    /// every stored job, oldest first
+    pub fn list(&self) -> Result<Vec<Job>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(anyhow!(e).context(format!("Failed to read job directory {}", self.dir.display()))),
+        };
+
+        let mut jobs: Vec<Job> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            // skips the temp files of writes that never finished
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| path.file_stem()?.to_str().map(String::from))
+            .filter_map(|id| self.get(&id))
+            .collect();
+        jobs.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(jobs)
+    }
+
+    // only ids we could have handed out, so a request can't point outside the directory
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric());
+        valid.then(|| self.dir.join(format!("{}.json", id)))
+    }
+
+    // unguessable, so one user of a shared server can't read another's jobs by counting
+    fn new_id(&self) -> String {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(self.next_id.fetch_add(1, Ordering::SeqCst));
+        hasher.write_u64(now_secs());
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// summarizes the job's video, recording every step in the store. failures are
+/// recorded in the job as well as returned
+pub fn run_job(summarizer: &VideoSummarizer, store: &JobStore, id: &str) -> Result<()> {
+    let job = store.get(id).with_context(|| format!("No job with id {}", id))?;
+
+    let result = summarizer.process_video_with_progress(&job.url, &|progress| {
+        // progress is best effort; the final state below is what counts
+        let _ = store.set_state(id, JobState::from(&progress));
+    });
+
+    let (state, summary, outcome) = match result {
+        Ok(summary) => (JobState::Done, Some(summary), Ok(())),
+        Err(e) => {
+            let state = JobState::Failed {
+                error: format!("{:#}", e),
+                error_kind: error::classify(&e).map(|kind| kind.kind().to_string()),
+            };
+            (state, None, Err(e))
+        }
+    };
+
+    store.update(id, |job| {
+        job.state = state;
+        job.summary = summary;
+    })?;
+    outcome
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_store(name: &str) -> (JobStore, PathBuf) {
+        let dir = env::temp_dir().join(format!("jobs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        (JobStore::new(&dir), dir)
+    }
+
+    #[test]
+    fn list_skips_leftover_temp_files() {
+        let (store, dir) = temp_store("tmp");
+        let job = store.create("https://youtu.be/dQw4w9WgXcQ").unwrap();
+        // what a crash between writing and renaming leaves behind
+        fs::write(dir.join(format!("{}.tmp123-0", job.id)), serde_json::to_string(&job).unwrap()).unwrap();
+
+        let ids: Vec<String> = store.list().unwrap().into_iter().map(|job| job.id).collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(ids, [job.id]);
+    }
+
+    #[test]
+    fn list_is_empty_without_a_directory() {
+        let (store, _) = temp_store("missing");
+        assert!(store.list().unwrap().is_empty());
+    }
+}
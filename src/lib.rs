@@ -0,0 +1,84 @@
+//! Summarizes YouTube videos: fetches a video's captions straight from YouTube,
+//! splits the transcript into chunks that fit the model, summarizes each chunk
+//! with a pluggable backend and merges the partial summaries.
+//!
+//! The `youtube_summarizer` binary is a thin command line layer over this crate.
+//! The main entry points are:
+//!
+//! - [`VideoRef::parse`] to get the video id out of any YouTube link
+//! - [`YouTubeTranscriptSource`] (a [`TranscriptSource`]) to fetch a transcript
+//! - [`Chunker`] to split text into model-sized chunks
+//! - [`VideoSummarizer`] to run the whole pipeline, [`VideoSummarizer::process_video`]
+//!   returning a [`Summary`]
+//! - [`output::render_summary`] to turn a [`Summary`] into text, JSON, markdown or subtitles
+//! - [`Server`] to offer all of the above as a REST api
+//!
+//! Backends implement [`Summarizer`]: [`HuggingFaceSummarizer`] for the Hugging
+//! Face Inference API, [`OpenAiSummarizer`] for OpenAI-compatible servers and
+//! [`TextRankSummarizer`] for offline extractive summaries. Failures worth
+//! telling apart are [`Error`]s inside the returned `anyhow::Error`; see
+//! [`error::classify`].
+//!
+//! The crate never prints: what it's doing is reported through [`tracing`], so
+//! install any subscriber to see it, and progress on a video can be followed
+//! with [`VideoSummarizer::process_video_with_progress`].
+//!
+//! ```no_run
+//! use youtube_summarizer::{output, OutputFormat, TextRankSummarizer, VideoSummarizer, YouTubeTranscriptSource};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let summarizer = VideoSummarizer::new(
+//!     Box::new(TextRankSummarizer::new()),
+//!     Box::new(YouTubeTranscriptSource::new().with_languages(vec!["de".into(), "en".into()])),
+//! );
+//!
+//! let summary = summarizer.process_video("https://youtu.be/dQw4w9WgXcQ")?;
+//! println!("{}", output::render_summary(&summary, OutputFormat::Markdown)?);
+//! # Ok(())
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod batch;
+pub mod cache;
+pub mod chapters;
+pub mod chunking;
+pub mod config;
+mod dirs;
+pub mod error;
+pub mod extractive;
+pub mod huggingface;
+pub mod job;
+pub mod openai;
+pub mod output;
+pub mod pipeline;
+pub mod playlist;
+pub mod rate_limit;
+pub mod retry;
+pub mod secret;
+pub mod server;
+pub mod summarizer;
+mod toml;
+pub mod transcript;
+pub mod translator;
+pub mod video_ref;
+
+pub use cache::SummaryCache;
+pub use chunking::{ApproxBpeTokenizer, Chunker, Tokenizer, WordTokenizer};
+pub use config::Config;
+pub use error::Error;
+pub use extractive::TextRankSummarizer;
+pub use huggingface::{HuggingFaceSummarizer, HuggingFaceTranslator};
+pub use job::{Job, JobState, JobStore};
+pub use openai::{OpenAiSummarizer, OpenAiTranslator};
+pub use output::OutputFormat;
+pub use pipeline::{PipelineOptions, Progress, Summary, VideoSummarizer};
+pub use rate_limit::RateLimiter;
+pub use retry::RetryPolicy;
+pub use secret::Secret;
+pub use server::Server;
+pub use summarizer::{Summarizer, SummarizerLimits};
+pub use transcript::{Transcript, TranscriptSegment, TranscriptSource, TranscriptTrack, YouTubeTranscriptSource};
+pub use translator::Translator;
+pub use video_ref::VideoRef;
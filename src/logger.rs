@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+const BAR_WIDTH: usize = 30;
+
+// the progress bar currently drawn on the last line of stderr, if any
+static BAR: Mutex<Option<String>> = Mutex::new(None);
+static SHOW_BAR: AtomicBool = AtomicBool::new(false);
+
+/// how log lines are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// plain messages, warnings and errors prefixed with their level
+    Text,
+    /// one json object per line, for log collectors
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("Unknown log format '{}', expected 'text' or 'json'", other)),
+        }
+    }
+}
+
+// "Warning: ..." rather than tracing's default of timestamps and targets
+struct TextFormat;
+
+impl<S, N> FormatEvent<S, N> for TextFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            Level::INFO => {}
+            level => write!(writer, "{}: ", level.as_str().to_lowercase())?,
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+// everything goes to stderr so stdout only ever holds the result
+struct Stderr;
+
+impl<'a> MakeWriter<'a> for Stderr {
+    type Writer = Stderr;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Stderr
+    }
+}
+
+impl Write for Stderr {
+    // gets one whole formatted event at a time. keep the bar below the log
+    // lines: wipe it, write the line, draw it again
+    fn write(&mut self, line: &[u8]) -> io::Result<usize> {
+        let bar = BAR.lock().unwrap();
+        let mut stderr = io::stderr().lock();
+        match bar.as_deref() {
+            Some(bar) => {
+                stderr.write_all(b"\r\x1b[2K")?;
+                stderr.write_all(line)?;
+                stderr.write_all(bar.as_bytes())?;
+            }
+            None => stderr.write_all(line)?,
+        }
+        Ok(line.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// installs the tracing subscriber, which also takes the `log` records of other
+/// crates. the progress bar is only drawn for text logs on a terminal, and not
+/// with `--quiet`
+pub fn init(level: LevelFilter, format: LogFormat) -> Result<()> {
+    // other crates (ureq) only get through with warnings and errors, unless
+    // everything was asked for
+    let others = if level == LevelFilter::TRACE { level } else { level.min(LevelFilter::WARN) };
+    let filter = Targets::new()
+        .with_target("youtube_summarizer", level)
+        .with_default(others);
+
+    let text = (format == LogFormat::Text).then(|| {
+        tracing_subscriber::fmt::layer().with_writer(Stderr).with_ansi(false).event_format(TextFormat)
+    });
+    let json = (format == LogFormat::Json).then(|| {
+        tracing_subscriber::fmt::layer().with_writer(Stderr).json().flatten_event(true)
+    });
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json)
+        .try_init()
+        .map_err(|e| anyhow!("Failed to install logger: {}", e))?;
+
+    SHOW_BAR.store(
+        format == LogFormat::Text && level >= LevelFilter::INFO && io::stderr().is_terminal(),
+        Ordering::SeqCst,
+    );
+    Ok(())
+}
+
+/// whether `progress` draws a bar; otherwise progress should be logged
+pub fn shows_bar() -> bool {
+    SHOW_BAR.load(Ordering::SeqCst)
+}
+
+/// draws `done` of `total` as a bar on the last line of stderr
+pub fn progress(label: &str, done: usize, total: usize) {
+    if !shows_bar() {
+        return;
+    }
+
+    let filled = (BAR_WIDTH * done.min(total)).checked_div(total).unwrap_or(BAR_WIDTH);
+    let line = format!("{} [{}{}] {}/{}", label, "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), done, total);
+
+    let mut bar = BAR.lock().unwrap();
+    let _ = write!(io::stderr().lock(), "\r\x1b[2K{}", line);
+    *bar = Some(line);
+}
+
+/// removes the bar, e.g. once the work it tracks is done or failed
+pub fn clear_progress() {
+    let mut bar = BAR.lock().unwrap();
+    if bar.take().is_some() {
+        let _ = write!(io::stderr().lock(), "\r\x1b[2K");
+    }
+}
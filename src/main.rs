@@ -1,198 +1,416 @@
-use anyhow::{Context, Result};
-use regex::Regex;
-use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
-use std::fs::File;
-use serde_json;
-use ureq;
-use std::process::Command;
-
-// struct to read config file for HF token
-#[derive(Debug, Deserialize)]
-struct Config {
-    token: String,
-}
+mod cli;
+mod logger;
 
-// main struct for summary
-#[derive(Debug, Serialize, Deserialize)]
-struct Summary {
-    video_id: Option<String>,
-    transcript: Option<String>,
-    summary: Option<String>,
-}
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing::level_filters::LevelFilter;
+use tracing::{error, info};
 
-// struct to store api response from HF transformer model
-#[derive(Debug, Deserialize)]
-struct ApiResponse {
-    summary_text: String,
-}
+use youtube_summarizer::batch::{self, BatchOptions};
+use youtube_summarizer::playlist::PlaylistResolver;
+use youtube_summarizer::{error, huggingface, openai, output, translator};
+use youtube_summarizer::{
+    ApproxBpeTokenizer, Config, Error, HuggingFaceSummarizer, HuggingFaceTranslator, JobStore, OpenAiSummarizer,
+    OpenAiTranslator, OutputFormat, PipelineOptions, Progress, RateLimiter, RetryPolicy, Server, Summarizer, SummarizerLimits, SummaryCache, TextRankSummarizer,
+    Tokenizer, TranscriptSource, Translator, VideoRef, VideoSummarizer, WordTokenizer, YouTubeTranscriptSource,
+};
 
-struct HuggingFaceSummarizer {
-    api_token: String,
-    api_url: String,
-}
+use cli::{Cli, Command};
 
-impl HuggingFaceSummarizer {
-    fn new(api_token: String) -> Self {
-        HuggingFaceSummarizer {
-            api_token,
-            api_url: "https://api-inference.huggingface.co/models/facebook/bart-large-cnn".to_string(),
+fn main() -> ExitCode {
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("Error: {:#}\n\n{}", e, cli::USAGE);
+            return ExitCode::from(2);
         }
+    };
+
+    let level = match (cli.quiet, cli.verbose) {
+        (true, _) => LevelFilter::WARN,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    if let Err(e) = logger::init(level, cli.log_format) {
+        eprintln!("Error: {:#}", e);
     }
 
-    fn extract_video_id(youtube_url: &str) -> Option<String> {
-        let re = Regex::new(r"(?:v=|/)([0-9A-Za-z_-]{11}).*").unwrap();
-        re.captures(youtube_url)
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str().to_string())
+    // prompting only makes sense when someone is there to answer
+    if cli.command == Command::Interactive && !io::stdin().is_terminal() {
+        eprintln!("Error: No command given\n\n{}", cli::USAGE);
+        return ExitCode::from(2);
     }
 
-    fn get_transcript(video_id: &str) -> Result<String> {
-        println!("Fetching transcript for video ID: {}", video_id);
-        
-        let output = Command::new("python3")
-            .arg("-c")
-            .arg(format!(
-                "from youtube_transcript_api import YouTubeTranscriptApi; \
-                 transcript = YouTubeTranscriptApi.get_transcript('{}'); \
-                 print(' '.join(entry['text'] for entry in transcript))",
-                video_id
-            ))
-            .output()
-            .context("Failed to execute python command. Make sure python3 and youtube_transcript_api are installed")?;
+    match run(&cli) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            logger::clear_progress();
+            error!("{:#}", e);
+            ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
 
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("Failed to get transcript: {}", error));
+// returns the exit status: 0 when every requested video succeeded, otherwise
+// the code of the failure category (see error::Error::exit_code)
+fn run(cli: &Cli) -> Result<u8> {
+    match &cli.command {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(0)
         }
+        Command::Transcript { url } => {
+            print_transcript(cli, url)?;
+            Ok(0)
+        }
+        Command::Summarize { urls } => {
+            let summarizer = build_summarizer(cli)?;
+            let mut failures = Vec::new();
+            // results for an output file are collected so one video doesn't overwrite another
+            let mut summaries = Vec::new();
+
+            for url in urls {
+                match summarizer.process_video_with_progress(url, &report_progress) {
+                    Ok(summary) if cli.output.is_some() => summaries.push(summary),
+                    Ok(summary) => write_output(None, &output::render_summary(&summary, cli.format)?)?,
+                    Err(e) => {
+                        logger::clear_progress();
+                        error!("{}: {:#}", url, e);
+                        failures.push(error::exit_code(&e));
+                    }
+                }
+            }
 
-        let transcript = String::from_utf8(output.stdout)
-            .context("Failed to parse transcript output")?;
+            if cli.output.is_some() && !summaries.is_empty() {
+                // several json documents in a row aren't json, so they go in an array
+                let content = if cli.format == OutputFormat::Json && urls.len() > 1 {
+                    serde_json::to_string_pretty(&summaries).context("Failed to serialize summaries")?
+                } else {
+                    let rendered = summaries.iter()
+                        .map(|summary| output::render_summary(summary, cli.format))
+                        .collect::<Result<Vec<_>>>()?;
+                    rendered.join("\n")
+                };
+                write_output(cli.output.as_deref(), &content)?;
+            }
+
+            Ok(error::combined_exit_code(failures))
+        }
+        Command::Batch { urls } => {
+            let mut inputs = urls.clone();
+            if let Some(path) = &cli.input {
+                inputs.extend(batch::read_url_file(path)?);
+            }
 
-        Ok(transcript)
+            let resolver = match env::var("YOUTUBE_BASE_URL") {
+                Ok(base_url) => PlaylistResolver::with_base_url(&base_url),
+                Err(_) => PlaylistResolver::new(),
+            };
+            let inputs = batch::expand_inputs(&inputs, &resolver);
+
+            let summarizer = build_summarizer(cli)?;
+            let options = BatchOptions {
+                jobs: cli.jobs,
+                output_dir: cli.output_dir.clone().into(),
+                format: cli.format,
+            };
+            let report = batch::run_batch(&summarizer, inputs, &options)?;
+
+            println!("{}", report.render());
+            Ok(error::combined_exit_code(report.items.iter().filter_map(|item| item.exit_code)))
+        }
+        Command::Serve => {
+            let config = load_config(cli)?;
+            let jobs_dir = cli.jobs_dir.clone()
+                .or(config.jobs_dir)
+                .map(PathBuf::from)
+                .unwrap_or_else(JobStore::default_dir);
+            let summarizer = build_summarizer(cli)?;
+            Server::new(summarizer, JobStore::new(jobs_dir), cli.jobs)?.serve(&cli.listen)?;
+            Ok(0)
+        }
+        Command::Interactive => {
+            let summarizer = build_summarizer(cli)?;
+
+            // on stderr like the rest of the chatter, stdout is for the summary
+            eprint!("Enter YouTube video URL: ");
+            io::stderr().flush()?;
+
+            let mut youtube_url = String::new();
+            io::stdin().read_line(&mut youtube_url)?;
+
+            let summary = summarizer.process_video_with_progress(youtube_url.trim(), &report_progress)?;
+            write_output(cli.output.as_deref(), &output::render_summary(&summary, cli.format)?)?;
+            Ok(0)
+        }
+    }
+}
+
+fn transcript_source(cli: &Cli, config: &Config) -> YouTubeTranscriptSource {
+    // YOUTUBE_BASE_URL lets the fetcher run against a local server replaying recorded pages
+    let source = match env::var("YOUTUBE_BASE_URL") {
+        Ok(base_url) => YouTubeTranscriptSource::with_base_url(&base_url),
+        Err(_) => YouTubeTranscriptSource::new(),
+    };
+
+    let source = match cli.languages.as_ref().or(config.languages.as_ref()) {
+        Some(languages) => source.with_languages(languages.clone()),
+        None => source,
+    };
+
+    // the other translators work on the fetched transcript instead
+    match translator_name(cli, config) {
+        "youtube" => source.with_translation(translate_to(cli, config)),
+        _ => source,
     }
+}
+
+fn translate_to(cli: &Cli, config: &Config) -> Option<String> {
+    cli.translate_to.clone().or(config.translate_to.clone())
+}
+
+fn translator_name<'a>(cli: &'a Cli, config: &'a Config) -> &'a str {
+    cli.translator.as_deref()
+        .or(config.translator.as_deref())
+        .unwrap_or("youtube")
+}
 
-    fn chunk_text(text: &str, max_length: usize) -> Vec<String> {
-        let words: Vec<&str> = text.split_whitespace().collect();
-        let mut chunks = Vec::new();
-        let mut current_chunk = Vec::new();
-        let mut current_length = 0;
+// translation backend for the pipeline; None when nothing should be translated
+// or youtube does it while fetching the transcript
+fn build_translator(cli: &Cli, config: &Config, retry: RetryPolicy) -> Result<Option<Box<dyn Translator>>> {
+    let Some(target) = translate_to(cli, config) else { return Ok(None) };
 
-        for word in words {
-            let word_len = word.len() + 1;
-            if current_length + word_len > max_length {
-                if !current_chunk.is_empty() {
-                    chunks.push(current_chunk.join(" "));
-                    current_chunk.clear();
-                    current_length = 0;
+    match translator_name(cli, config) {
+        "youtube" => Ok(None),
+        "huggingface" => {
+            let token = config.resolve_token()?.ok_or_else(|| Error::Auth(
+                "the huggingface translator needs a Hugging Face token; set HF_TOKEN, \"token_command\" or \"token_file\" in the config".to_string()
+            ))?;
+            let model = match (&config.translation_model, translator::is_same_language(&target, "en")) {
+                (Some(model), _) => model.as_str(),
+                (None, true) => huggingface::DEFAULT_TRANSLATION_MODEL,
+                (None, false) => {
+                    return Err(anyhow::anyhow!(
+                        "Set \"translation_model\" to a model that translates into '{}', e.g. Helsinki-NLP/opus-mt-en-{}",
+                        target,
+                        target
+                    ));
                 }
+            };
+
+            let mut translator = HuggingFaceTranslator::new(token, model, &target)
+                .with_wait_for_model(config.wait_for_model)
+                .with_retry_policy(retry);
+            if let Some(endpoint) = &config.translation_endpoint {
+                translator = translator.with_endpoint(endpoint);
             }
-            current_chunk.push(word);
-            current_length += word_len;
+            Ok(Some(Box::new(translator)))
         }
+        "openai" => {
+            // fall back to the summarizer's model and server when that is an openai one too
+            let summarizer_is_openai = cli.backend.as_deref().or(config.backend.as_deref()) == Some("openai");
+            let model = config.translation_model.as_ref()
+                .or(cli.model.as_ref().or(config.model.as_ref()).filter(|_| summarizer_is_openai))
+                .context("The openai translator needs a model name; set \"translation_model\" in the config")?;
+            let base_url = config.translation_endpoint.as_deref()
+                .or(cli.endpoint.as_deref().or(config.endpoint.as_deref()).filter(|_| summarizer_is_openai))
+                .unwrap_or(openai::DEFAULT_BASE_URL);
 
-        if !current_chunk.is_empty() {
-            chunks.push(current_chunk.join(" "));
+            let mut translator = OpenAiTranslator::new(base_url, model, &target)
+                .with_api_key(config.api_key.clone())
+                .with_retry_policy(retry);
+            if let Some(tokens) = config.context_tokens {
+                translator = translator.with_context_tokens(tokens);
+            }
+            Ok(Some(Box::new(translator)))
         }
+        other => Err(anyhow::anyhow!("Unknown translator '{}', expected 'youtube', 'huggingface' or 'openai'", other)),
+    }
+}
+
+fn load_config(cli: &Cli) -> Result<Config> {
+    Config::load(cli.config.as_deref(), cli.profile.as_deref())
+        .context("Failed to read config")
+}
+
+// builds the pipeline from the config file, with command line flags taking precedence
+fn build_summarizer(cli: &Cli) -> Result<VideoSummarizer> {
+    let config = load_config(cli)?;
 
-        chunks
+    let mut options = PipelineOptions::default();
+    options.chapters = config.chapters || cli.chapters;
+    if let Some(window) = config.chapter_window {
+        options.chapter_window_secs = window;
+    }
+    if let Some(depth) = config.reduce_depth {
+        options.reduce_depth = depth;
+    }
+    if let Some(length) = config.summary_length {
+        options.summary_length = length;
+    }
+    options.chunk_tokens = cli.chunk_tokens.or(config.chunk_tokens);
+    if let Some(overlap) = config.chunk_overlap {
+        options.chunk_overlap = overlap;
+    }
+    if let Some(workers) = cli.workers.or(config.workers) {
+        options.workers = workers.max(1);
     }
 
-    fn summarize_text(&self, text: &str) -> Result<String> {
-        let chunks = Self::chunk_text(text, 1024);
-        let mut summaries = Vec::new();
-        
-        println!("Processing {} chunks...", chunks.len());
+    let tokenizer: Box<dyn Tokenizer> = match config.tokenizer.as_deref() {
+        None | Some("bpe") => Box::new(ApproxBpeTokenizer),
+        Some("words") => Box::new(WordTokenizer),
+        Some(other) => return Err(anyhow::anyhow!("Unknown tokenizer '{}', expected 'bpe' or 'words'", other)),
+    };
 
-        for (i, chunk) in chunks.iter().enumerate() {
-            println!("Summarizing chunk {}/{}...", i + 1, chunks.len());
-            
-            let response = ureq::post(&self.api_url)
-                .set("Authorization", &format!("Bearer {}", self.api_token))
-                .send_json(ureq::json!({
-                    "inputs": chunk,
-                    "parameters": {
-                        "max_length": 150,
-                        "min_length": 30,
-                        "do_sample": false
-                    }
-                }))
-                .context("Failed to send request to API")?;
-
-            let summary: Vec<ApiResponse> = response.into_json()
-                .context("Failed to parse API response")?;
-            
-            if let Some(first_summary) = summary.first() {
-                summaries.push(first_summary.summary_text.clone());
+    let retry = retry_policy(cli, &config)?;
+
+    let backend: Box<dyn Summarizer> = match cli.backend.as_deref().or(config.backend.as_deref()) {
+        None | Some("huggingface") => {
+            let token = config.resolve_token()?.ok_or_else(|| Error::Auth(
+                "no Hugging Face token configured; set HF_TOKEN, \"token_command\" or \"token_file\" in the config, or use --backend extractive".to_string()
+            ))?;
+
+            let mut backend = HuggingFaceSummarizer::new(token)
+                .with_wait_for_model(config.wait_for_model)
+                .with_retry_policy(retry.clone());
+            if let Some(model) = cli.model.as_ref().or(config.model.as_ref()) {
+                backend = backend.with_model(model);
+            }
+            if let Some(endpoint) = cli.endpoint.as_ref().or(config.endpoint.as_ref()) {
+                backend = backend.with_endpoint(endpoint);
             }
+            let (min_length, max_length) = length_bounds(cli, &config, backend.limits())?;
+            Box::new(backend
+                .with_length_bounds(min_length, max_length)
+                .with_sampling(cli.do_sample.or(config.do_sample).unwrap_or(false), cli.temperature.or(config.temperature))
+                .with_num_beams(cli.num_beams.or(config.num_beams)))
         }
+        Some("openai") => {
+            let model = cli.model.as_ref().or(config.model.as_ref())
+                .context("The openai backend needs a model name; set \"model\" in the config or use --model")?;
+            let base_url = cli.endpoint.as_deref()
+                .or(config.endpoint.as_deref())
+                .unwrap_or(openai::DEFAULT_BASE_URL);
 
-        if summaries.is_empty() {
-            return Err(anyhow::anyhow!("No summaries were generated"));
+            let mut backend = OpenAiSummarizer::new(base_url, model)
+                .with_api_key(config.api_key.clone())
+                .with_retry_policy(retry.clone());
+            if let Some(prompt) = &config.system_prompt {
+                backend = backend.with_system_prompt(prompt);
+            }
+            if let Some(template) = &config.prompt_template {
+                backend = backend.with_prompt_template(template)?;
+            }
+            if let Some(path) = cli.prompt_file.as_ref().or(config.prompt_file.as_ref()) {
+                backend = backend.with_prompt_template_file(path)?;
+            }
+            if let Some(tokens) = config.context_tokens {
+                backend = backend.with_context_tokens(tokens);
+            }
+            if let Some(temperature) = cli.temperature.or(config.temperature) {
+                backend = backend.with_temperature(temperature);
+            }
+            let (min_length, max_length) = length_bounds(cli, &config, backend.limits())?;
+            Box::new(backend.with_length_bounds(min_length, max_length))
+        }
+        Some("extractive") => {
+            let backend = TextRankSummarizer::new();
+            let (min_length, max_length) = length_bounds(cli, &config, backend.limits())?;
+            Box::new(backend.with_length_bounds(min_length, max_length))
+        }
+        Some(other) => {
+            return Err(anyhow::anyhow!("Unknown backend '{}', expected 'huggingface', 'openai' or 'extractive'", other));
         }
+    };
 
-        // Join all summaries with newlines between them
-        Ok(summaries.join("\n\n"))
+    let mut summarizer = VideoSummarizer::new(backend, Box::new(transcript_source(cli, &config)))
+        .with_tokenizer(tokenizer)
+        .with_options(options);
+    // the translator has a quota of its own
+    if let Some(translator) = build_translator(cli, &config, retry_policy(cli, &config)?)? {
+        summarizer = summarizer.with_translator(translator);
     }
 
-    fn process_video(&self, youtube_url: &str) -> Result<Summary> {
-        let video_id = Self::extract_video_id(youtube_url)
-            .context("Failed to extract video ID from URL")?;
-        
-        println!("Extracted video ID: {}", video_id);
-        
-        let mut result = Summary {
-            video_id: Some(video_id.clone()),
-            transcript: None,
-            summary: None,
-        };
-
-        let transcript = Self::get_transcript(&video_id)?;
-        result.transcript = Some(transcript.clone());
-        
-        let summary = self.summarize_text(&transcript)?;
-        result.summary = Some(summary);
-
-        Ok(result)
+    if !cli.no_cache && config.cache.unwrap_or(true) {
+        let dir = cli.cache_dir.clone()
+            .or(config.cache_dir)
+            .map(PathBuf::from)
+            .unwrap_or_else(SummaryCache::default_dir);
+        summarizer = summarizer.with_cache(SummaryCache::new(dir));
     }
-}
 
-fn read_config(path: &str) -> Result<String> {
-    let file = File::open(path)
-        .context(format!("Failed to open config file at {}", path))?;
-    
-    let config: Config = serde_json::from_reader(file)
-        .context("Failed to parse config file")?;
-    
-    Ok(config.token)
+    Ok(summarizer)
 }
 
-fn main() -> Result<()> {
-    let config_path = "config.json"; //path to Hugging Face Access token here
-    
-    let api_token = read_config(config_path)
-        .context("Failed to read API token from config")?;
+// retries from the config, and a fresh rate limiter for the backend the policy is for
+fn retry_policy(cli: &Cli, config: &Config) -> Result<RetryPolicy> {
+    let mut retry = RetryPolicy::default();
+    if let Some(max_retries) = config.max_retries {
+        retry.max_retries = max_retries;
+    }
+
+    if let Some(rate_limit) = cli.rate_limit.or(config.rate_limit) {
+        retry = retry.with_rate_limiter(RateLimiter::per_minute(rate_limit, config.rate_limit_burst.unwrap_or(1))?);
+    }
 
-    let summarizer = HuggingFaceSummarizer::new(api_token);
+    Ok(retry)
+}
 
-    print!("Enter YouTube video URL: ");
-    io::stdout().flush()?;
+// summary length bounds: command line, then environment and config file, then
+// the backend's own defaults
+fn length_bounds(cli: &Cli, config: &Config, defaults: SummarizerLimits) -> Result<(usize, usize)> {
+    let min_length = cli.min_length.or(config.min_length).unwrap_or(defaults.min_summary_length);
+    let max_length = cli.max_length.or(config.max_length).unwrap_or(defaults.max_summary_length);
+    if min_length > max_length {
+        return Err(anyhow::anyhow!(
+            "min_length ({}) must not exceed max_length ({})",
+            min_length,
+            max_length
+        ));
+    }
+    Ok((min_length, max_length))
+}
 
-    let mut youtube_url = String::new();
-    io::stdin().read_line(&mut youtube_url)?;
+// writes rendered output to a file, or stdout when no path is given
+fn write_output(path: Option<&str>, content: &str) -> Result<()> {
+    match path {
+        Some(path) => fs::write(path, content)
+            .context(format!("Failed to write output to {}", path)),
+        None => {
+            println!("{}", content.trim_end());
+            Ok(())
+        }
+    }
+}
 
-    match summarizer.process_video(&youtube_url) {
-        Ok(result) => {
-            if let Some(summary) = result.summary {
-                println!("\nVideo Summary:");
-                println!("{}", "-".repeat(50));
-                println!("{}", summary);
+// a bar on a terminal, log lines otherwise
+fn report_progress(progress: Progress) {
+    match progress {
+        Progress::Summarizing { done, total } if logger::shows_bar() => {
+            if done < total {
+                logger::progress("Summarizing", done, total);
             } else {
-                println!("Failed to generate summary");
+                logger::clear_progress();
+                info!("Summarized {} chunks", total);
             }
         }
-        Err(e) => println!("Error: {:#}", e),
+        Progress::Summarizing { done, total } if done > 0 => info!("Summarized {}/{} chunks", done, total),
+        Progress::Reducing { pass, max_passes } => info!("Reducing summaries (pass {}/{})...", pass, max_passes),
+        // the transcript source reports fetching itself
+        Progress::FetchingTranscript { .. } | Progress::Summarizing { .. } => {}
     }
+}
 
-    Ok(())
-}
\ No newline at end of file
+fn print_transcript(cli: &Cli, youtube_url: &str) -> Result<()> {
+    let config = load_config(cli)?;
+    let video_id = VideoRef::parse(youtube_url)?.id;
+    let transcript = transcript_source(cli, &config).fetch_transcript(&video_id)?;
+
+    let rendered = output::render_transcript(&video_id, &transcript.segments, cli.format)?;
+    write_output(cli.output.as_deref(), &rendered)
+}
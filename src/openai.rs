@@ -0,0 +1,305 @@
+//! Summarizing and translating with OpenAI-compatible servers such as llama.cpp,
+//! vLLM and Ollama.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+
+use crate::error::Error;
+use crate::retry::RetryPolicy;
+use crate::secret::Secret;
+use crate::summarizer::{Summarizer, SummarizerLimits};
+use crate::translator::Translator;
+
+/// where the llama.cpp server listens by default
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8080/v1";
+
+const DEFAULT_SYSTEM_PROMPT: &str = "You summarize video transcripts accurately and concisely.";
+
+// placeholders: {text}, {min_length}, {max_length}
+const DEFAULT_PROMPT_TEMPLATE: &str = "\
+Summarize the following part of a video transcript in {min_length} to {max_length} words. \
+Keep the key points and facts, and reply with the summary only.
+
+Transcript:
+{text}";
+
+const TRANSLATION_SYSTEM_PROMPT: &str = "You translate video transcripts faithfully.";
+
+const TRANSLATION_PROMPT: &str = "\
+Translate the following part of a video transcript into the language with code '{language}'. \
+Keep its meaning and tone, and reply with the translation only.
+
+Transcript:
+{text}";
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+/// summarizer for any server speaking the OpenAI /v1/chat/completions api:
+/// llama.cpp server, vLLM, Ollama and the like
+pub struct OpenAiSummarizer {
+    base_url: String,
+    model: String,
+    api_key: Option<Secret>,
+    name: String,
+    system_prompt: String,
+    prompt_template: String,
+    // tokens of transcript sent per request, leaving room for the prompt and reply
+    context_tokens: usize,
+    min_length: usize,
+    max_length: usize,
+    temperature: f64,
+    retry: RetryPolicy,
+}
+
+impl OpenAiSummarizer {
+    /// `base_url` is the api root, e.g. "http://localhost:11434/v1" for Ollama
+    pub fn new(base_url: &str, model: &str) -> Self {
+        OpenAiSummarizer {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key: None,
+            name: format!("openai:{}", model),
+            system_prompt: DEFAULT_SYSTEM_PROMPT.to_string(),
+            prompt_template: DEFAULT_PROMPT_TEMPLATE.to_string(),
+            context_tokens: 3000,
+            min_length: 30,
+            max_length: 150,
+            temperature: 0.2,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// sent as a bearer token, for servers that need one
+    pub fn with_api_key(mut self, api_key: Option<Secret>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// replaces the default system message
+    pub fn with_system_prompt(mut self, prompt: &str) -> Self {
+        self.system_prompt = prompt.to_string();
+        self
+    }
+
+    /// the template must contain {text}; {min_length} and {max_length} are optional
+    pub fn with_prompt_template(mut self, template: &str) -> Result<Self> {
+        if !template.contains("{text}") {
+            return Err(anyhow!("Prompt template must contain the {{text}} placeholder"));
+        }
+        self.prompt_template = template.to_string();
+        Ok(self)
+    }
+
+    /// like `with_prompt_template`, reading the template from a file
+    pub fn with_prompt_template_file(self, path: &str) -> Result<Self> {
+        let template = fs::read_to_string(path)
+            .context(format!("Failed to read prompt template {}", path))?;
+        self.with_prompt_template(&template)
+    }
+
+    /// tokens of transcript sent per request; keep well below the model's context
+    /// window to leave room for the prompt and the reply
+    pub fn with_context_tokens(mut self, context_tokens: usize) -> Self {
+        self.context_tokens = context_tokens;
+        self
+    }
+
+    /// bounds on the length of each summary, in words as far as the model follows the prompt
+    pub fn with_length_bounds(mut self, min_length: usize, max_length: usize) -> Self {
+        self.min_length = min_length;
+        self.max_length = max_length;
+        self
+    }
+
+    /// sampling temperature, 0.2 by default
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// how rate limits and loading models are retried
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn render_prompt(&self, chunk: &str) -> String {
+        self.prompt_template
+            .replace("{min_length}", &self.min_length.to_string())
+            .replace("{max_length}", &self.max_length.to_string())
+            .replace("{text}", chunk)
+    }
+}
+
+impl Summarizer for OpenAiSummarizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        "chat"
+    }
+
+    fn limits(&self) -> SummarizerLimits {
+        SummarizerLimits {
+            max_input_tokens: self.context_tokens,
+            min_summary_length: self.min_length,
+            max_summary_length: self.max_length,
+        }
+    }
+
+    fn summarize_chunk(&self, chunk: &str) -> Result<String> {
+        let body = ureq::json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": self.system_prompt },
+                { "role": "user", "content": self.render_prompt(chunk) }
+            ],
+            // words to tokens, with headroom so the reply isn't cut off mid-sentence
+            "max_tokens": self.max_length * 2,
+            "temperature": self.temperature,
+            "stream": false
+        });
+
+        chat_completion(&self.retry, &self.base_url, self.api_key.as_ref(), &body)?
+            .context("API response contained no summary")
+    }
+
+    fn fingerprint(&self) -> String {
+        format!(
+            "{}|{}|{:?}|{}|{}|{}",
+            self.name,
+            self.base_url,
+            self.limits(),
+            self.temperature,
+            self.system_prompt,
+            self.prompt_template
+        )
+    }
+}
+
+/// translator using an instruction-tuned model behind an OpenAI-compatible api
+pub struct OpenAiTranslator {
+    base_url: String,
+    model: String,
+    api_key: Option<Secret>,
+    name: String,
+    target_language: String,
+    // tokens of transcript sent per request
+    context_tokens: usize,
+    retry: RetryPolicy,
+}
+
+impl OpenAiTranslator {
+    /// `base_url` is the api root, e.g. "http://localhost:11434/v1" for Ollama
+    pub fn new(base_url: &str, model: &str, target_language: &str) -> Self {
+        OpenAiTranslator {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key: None,
+            name: format!("openai:{}", model),
+            target_language: target_language.to_string(),
+            context_tokens: 1500,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// sent as a bearer token, for servers that need one
+    pub fn with_api_key(mut self, api_key: Option<Secret>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// tokens of transcript sent per request; keep well below the model's context
+    /// window to leave room for the prompt and the reply
+    pub fn with_context_tokens(mut self, context_tokens: usize) -> Self {
+        self.context_tokens = context_tokens;
+        self
+    }
+
+    /// how rate limits and loading models are retried
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+impl Translator for OpenAiTranslator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn target_language(&self) -> &str {
+        &self.target_language
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.context_tokens
+    }
+
+    fn translate_chunk(&self, chunk: &str) -> Result<String> {
+        let prompt = TRANSLATION_PROMPT
+            .replace("{language}", &self.target_language)
+            .replace("{text}", chunk);
+        let body = ureq::json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": TRANSLATION_SYSTEM_PROMPT },
+                { "role": "user", "content": prompt }
+            ],
+            // translations run longer than their source, leave plenty of room
+            "max_tokens": self.context_tokens * 2,
+            "temperature": 0.0,
+            "stream": false
+        });
+
+        chat_completion(&self.retry, &self.base_url, self.api_key.as_ref(), &body)?
+            .context("API response contained no translation")
+    }
+
+    fn fingerprint(&self) -> String {
+        format!("{}|{}|{}|{}", self.name, self.base_url, self.target_language, self.context_tokens)
+    }
+}
+
+// posts a chat completion request and returns the reply, None when it's empty
+fn chat_completion(retry: &RetryPolicy, base_url: &str, api_key: Option<&Secret>, body: &Value) -> Result<Option<String>> {
+    let url = format!("{}/chat/completions", base_url);
+
+    let response = retry
+        .send_json(
+            || {
+                let request = ureq::post(&url);
+                match api_key {
+                    Some(key) => request.set("Authorization", &format!("Bearer {}", key.expose())),
+                    None => request,
+                }
+            },
+            body,
+        )
+        .context(format!("Failed to send request to API: {}", url))?;
+
+    let response: ChatResponse = response.into_json()
+        .map_err(|e| Error::ResponseParse(e.to_string()))?;
+
+    Ok(response.choices.into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty()))
+}
@@ -0,0 +1,269 @@
+//! Rendering summaries and transcripts as text, JSON, markdown or subtitles.
+
+use anyhow::{anyhow, Context, Result};
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::chapters::{self, format_timestamp};
+use crate::pipeline::Summary;
+use crate::transcript::{self, TranscriptSegment};
+
+/// how a summary or transcript is written out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// plain text for reading in a terminal
+    Text,
+    /// the full `Summary`, or the transcript segments
+    Json,
+    /// headings and timestamp links, for wikis and notes apps
+    Markdown,
+    /// SubRip subtitles of the transcript
+    Srt,
+    /// WebVTT subtitles of the transcript
+    Vtt,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "srt" => Ok(OutputFormat::Srt),
+            "vtt" | "webvtt" => Ok(OutputFormat::Vtt),
+            other => Err(anyhow!(
+                "Unknown output format '{}', expected text, json, markdown, srt or vtt",
+                other
+            )),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// file extension for files in this format, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Srt => "srt",
+            OutputFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// renders a finished summary. srt and vtt export the transcript it was made from
+pub fn render_summary(summary: &Summary, format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(summary)
+            .context("Failed to serialize summary"),
+        OutputFormat::Text => render_text(summary),
+        OutputFormat::Markdown => Ok(render_markdown(summary)),
+        OutputFormat::Srt | OutputFormat::Vtt => {
+            let segments = summary.transcript.as_deref().unwrap_or_default();
+            Ok(render_subtitles(segments, format == OutputFormat::Vtt))
+        }
+    }
+}
+
+/// renders a bare transcript, for the transcript command
+pub fn render_transcript(video_id: &str, segments: &[TranscriptSegment], format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(segments)
+            .context("Failed to serialize transcript"),
+        OutputFormat::Text => Ok(transcript::transcript_text(segments)),
+        OutputFormat::Markdown => {
+            let mut out = format!("# Transcript of {}\n\n", video_id);
+            for segment in segments {
+                let _ = writeln!(
+                    out,
+                    "[{}]({}) {}  ",
+                    format_timestamp(segment.start),
+                    chapters::watch_url(video_id, segment.start),
+                    segment.text
+                );
+            }
+            Ok(out)
+        }
+        OutputFormat::Srt | OutputFormat::Vtt => Ok(render_subtitles(segments, format == OutputFormat::Vtt)),
+    }
+}
+
+fn render_text(summary: &Summary) -> Result<String> {
+    let mut out = String::new();
+
+    if let Some(chapters) = &summary.chapters {
+        out.push_str("\nVideo Summary by Chapter:\n");
+        out.push_str(&"-".repeat(50));
+        out.push('\n');
+        for chapter in chapters {
+            let title = chapter.title.as_deref().unwrap_or("");
+            let _ = write!(
+                out,
+                "\n[{}] {}\n{}\n{}\n",
+                format_timestamp(chapter.start),
+                title,
+                chapter.url,
+                chapter.summary
+            );
+        }
+    } else if let Some(text) = &summary.summary {
+        out.push_str("\nVideo Summary:\n");
+        out.push_str(&"-".repeat(50));
+        let _ = writeln!(out, "\n{}", text);
+    } else {
+        return Err(anyhow!("Failed to generate summary"));
+    }
+
+    Ok(out)
+}
+
+fn render_markdown(summary: &Summary) -> String {
+    let video_id = summary.video_id.as_deref().unwrap_or_default();
+    let mut out = format!("# Summary of {}\n\n", video_id);
+
+    let _ = writeln!(out, "[Watch on YouTube]({})\n", chapters::watch_url(video_id, 0.0));
+    if let Some(model) = &summary.model {
+        let _ = writeln!(out, "_Summarized with {}_\n", model);
+    }
+    if let Some(track) = &summary.transcript_track {
+        // youtube's names already say "(auto-generated)"
+        let label = match &track.name {
+            Some(name) => name.clone(),
+            None if track.generated => format!("{} (auto-generated)", track.language),
+            None => track.language.clone(),
+        };
+        let _ = writeln!(out, "_Transcript: {}_\n", label);
+    }
+
+    if let Some(text) = &summary.summary {
+        let _ = writeln!(out, "## Summary\n\n{}\n", text);
+    }
+
+    if let Some(chapters) = &summary.chapters {
+        out.push_str("## Chapters\n\n");
+        for chapter in chapters {
+            let title = chapter.title.as_deref().unwrap_or("");
+            let _ = writeln!(
+                out,
+                "### [{}]({}) {}\n\n{}\n",
+                format_timestamp(chapter.start),
+                chapter.url,
+                title,
+                chapter.summary
+            );
+        }
+    }
+
+    out
+}
+
+// srt numbers its cues and uses a comma before the milliseconds, webvtt has a
+// header and uses a dot
+fn render_subtitles(segments: &[TranscriptSegment], vtt: bool) -> String {
+    let mut out = String::new();
+    if vtt {
+        out.push_str("WEBVTT\n\n");
+    }
+
+    for (i, segment) in segments.iter().enumerate() {
+        if !vtt {
+            let _ = writeln!(out, "{}", i + 1);
+        }
+        let _ = writeln!(
+            out,
+            "{} --> {}\n{}\n",
+            cue_time(segment.start, vtt),
+            cue_time(segment.start + segment.duration, vtt),
+            segment.text
+        );
+    }
+
+    out
+}
+
+fn cue_time(seconds: f64, vtt: bool) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (h, m, s, ms) = (millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000);
+    let separator = if vtt { '.' } else { ',' };
+    format!("{:02}:{:02}:{:02}{}{:03}", h, m, s, separator, ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::ChapterSummary;
+
+    const ID: &str = "dQw4w9WgXcQ";
+
+    fn segment(start: f64, duration: f64, text: &str) -> TranscriptSegment {
+        TranscriptSegment { start, duration, text: text.to_string() }
+    }
+
+    fn summary() -> Summary {
+        Summary {
+            video_id: Some(ID.to_string()),
+            url: Some(format!("https://youtu.be/{}", ID)),
+            model: Some("fake (1)".to_string()),
+            transcript: Some(vec![segment(0.0, 2.5, "Hello"), segment(2.5, 1.25, "World")]),
+            transcript_track: None,
+            translator: None,
+            summary: Some("Intro.\n\nSetup.".to_string()),
+            chapters: Some(vec![
+                ChapterSummary { title: Some("Intro".to_string()), start: 0.0, end: 90.0, url: chapters::watch_url(ID, 0.0), summary: "Intro.".to_string() },
+                ChapterSummary { title: Some("Setup".to_string()), start: 90.0, end: 200.0, url: chapters::watch_url(ID, 90.0), summary: "Setup.".to_string() },
+            ]),
+        }
+    }
+
+    #[test]
+    fn cue_times_use_a_comma_for_srt_and_a_dot_for_vtt() {
+        assert_eq!(cue_time(0.0, false), "00:00:00,000");
+        assert_eq!(cue_time(3661.5, false), "01:01:01,500");
+        assert_eq!(cue_time(3661.5, true), "01:01:01.500");
+        assert_eq!(cue_time(59.9996, false), "00:01:00,000");
+        assert_eq!(cue_time(3599.999, true), "00:59:59.999");
+        assert_eq!(cue_time(360_000.0, true), "100:00:00.000");
+        assert_eq!(cue_time(-1.0, false), "00:00:00,000");
+    }
+
+    #[test]
+    fn srt_numbers_its_cues() {
+        let srt = render_summary(&summary(), OutputFormat::Srt).unwrap();
+        assert_eq!(srt, "1\n00:00:00,000 --> 00:00:02,500\nHello\n\n2\n00:00:02,500 --> 00:00:03,750\nWorld\n\n");
+    }
+
+    #[test]
+    fn vtt_has_a_header_and_no_cue_numbers() {
+        let vtt = render_transcript(ID, &summary().transcript.unwrap(), OutputFormat::Vtt).unwrap();
+        assert_eq!(vtt, "WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nHello\n\n00:00:02.500 --> 00:00:03.750\nWorld\n\n");
+    }
+
+    #[test]
+    fn markdown_links_chapters_to_their_timestamps() {
+        let markdown = render_summary(&summary(), OutputFormat::Markdown).unwrap();
+        assert!(markdown.starts_with(&format!("# Summary of {}\n\n[Watch on YouTube](https://www.youtube.com/watch?v={}&t=0s)\n", ID, ID)), "{}", markdown);
+        assert!(markdown.contains("_Summarized with fake (1)_"), "{}", markdown);
+        assert!(markdown.contains(&format!("### [0:00](https://www.youtube.com/watch?v={}&t=0s) Intro\n\nIntro.\n", ID)), "{}", markdown);
+        assert!(markdown.contains(&format!("### [1:30](https://www.youtube.com/watch?v={}&t=90s) Setup\n\nSetup.\n", ID)), "{}", markdown);
+    }
+
+    #[test]
+    fn markdown_transcripts_link_every_line() {
+        let markdown = render_transcript(ID, &summary().transcript.unwrap(), OutputFormat::Markdown).unwrap();
+        assert_eq!(markdown, format!(
+            "# Transcript of {id}\n\n[0:00](https://www.youtube.com/watch?v={id}&t=0s) Hello  \n[0:02](https://www.youtube.com/watch?v={id}&t=2s) World  \n",
+            id = ID
+        ));
+    }
+
+    #[test]
+    fn json_round_trips_the_summary() {
+        let json = render_summary(&summary(), OutputFormat::Json).unwrap();
+        let parsed: Summary = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.chapters.unwrap().len(), 2);
+        assert_eq!(parsed.video_id.as_deref(), Some(ID));
+    }
+}
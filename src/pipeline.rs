@@ -0,0 +1,719 @@
+//! The summarization pipeline: transcript, chunks, chunk summaries, reduced summary.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use tracing::{debug, info, warn};
+
+use crate::cache::SummaryCache;
+use crate::chapters::{self, ChapterSummary};
+use crate::chunking::{self, ApproxBpeTokenizer, Chunker, Tokenizer};
+use crate::summarizer::Summarizer;
+use crate::transcript::{Transcript, TranscriptSegment, TranscriptSource, TranscriptTrack};
+use crate::translator::{self, Translator};
+use crate::video_ref::VideoRef;
+
+/// main struct for summary. fields may be added, so it can't be built outside
+/// this crate; get one from `VideoSummarizer::process_video`
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Summary {
+    /// the 11 character youtube id
+    pub video_id: Option<String>,
+    /// the link as it was given
+    pub url: Option<String>,
+    /// backend name and version that produced the summary
+    pub model: Option<String>,
+    /// the transcript as fetched, before any translator ran
+    pub transcript: Option<Vec<TranscriptSegment>>,
+    /// caption track the transcript came from
+    pub transcript_track: Option<TranscriptTrack>,
+    /// backend that translated the transcript before summarizing and the language it
+    /// translated into, e.g. "openai (en)", if one did. translations youtube made
+    /// are recorded in `transcript_track` instead
+    pub translator: Option<String>,
+    /// the summary of the whole video. with chapters, the chapter summaries joined
+    pub summary: Option<String>,
+    /// one summary per chapter, when summarizing by chapter
+    pub chapters: Option<Vec<ChapterSummary>>,
+}
+
+/// a step of a run, reported to the callback given to `process_video_with_progress`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    /// downloading the captions, or reading them from the cache
+    FetchingTranscript {
+        /// the video's youtube id
+        video_id: String,
+    },
+    /// summarizing the transcript chunks. with chapters the count starts over
+    /// for every chapter
+    Summarizing {
+        /// chunks summarized so far
+        done: usize,
+        /// chunks to summarize
+        total: usize,
+    },
+    /// summarizing the joined chunk summaries again
+    Reducing {
+        /// the current pass, counting from 1
+        pass: usize,
+        /// the most passes that will be made
+        max_passes: usize,
+    },
+}
+
+// what the steps of one video's run share
+#[derive(Clone, Copy)]
+struct Run<'a> {
+    video_id: &'a str,
+    // run each chunk through the translator first
+    translate: bool,
+    progress: &'a (dyn Fn(Progress) + Sync),
+}
+
+/// knobs for how a transcript is turned into a summary. options may be added, so
+/// start from `PipelineOptions::default()` and change the fields you need
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PipelineOptions {
+    /// summarize each chapter separately instead of the whole transcript at once
+    pub chapters: bool,
+    /// window length used for chapters when the description has no chapter markers
+    pub chapter_window_secs: f64,
+    /// how many times the joined chunk summaries may be summarized again
+    pub reduce_depth: usize,
+    /// reduce passes stop once the summary is at most this many characters
+    pub summary_length: usize,
+    /// chunk size in tokens, defaults to the backend's input limit
+    pub chunk_tokens: Option<usize>,
+    /// tokens of trailing sentences repeated at the start of the next chunk
+    pub chunk_overlap: usize,
+    /// chunks sent to the backend at the same time
+    pub workers: usize,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        PipelineOptions {
+            chapters: false,
+            chapter_window_secs: 300.0,
+            reduce_depth: 3,
+            summary_length: 2000,
+            chunk_tokens: None,
+            chunk_overlap: 0,
+            workers: 4,
+        }
+    }
+}
+
+/// fetches transcripts and runs them through a summarization backend
+pub struct VideoSummarizer {
+    summarizer: Box<dyn Summarizer>,
+    transcripts: Box<dyn TranscriptSource>,
+    translator: Option<Box<dyn Translator>>,
+    tokenizer: Box<dyn Tokenizer>,
+    cache: Option<SummaryCache>,
+    options: PipelineOptions,
+}
+
+impl VideoSummarizer {
+    /// summarizes with `summarizer` whatever `transcripts` fetches, with the default
+    /// tokenizer and options, no translator and no cache
+    pub fn new(summarizer: Box<dyn Summarizer>, transcripts: Box<dyn TranscriptSource>) -> Self {
+        VideoSummarizer {
+            summarizer,
+            transcripts,
+            translator: None,
+            tokenizer: Box::new(ApproxBpeTokenizer),
+            cache: None,
+            options: PipelineOptions::default(),
+        }
+    }
+
+    /// how chunk sizes are measured; should match the backend's model
+    pub fn with_tokenizer(mut self, tokenizer: Box<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// translates transcripts that aren't in the translator's target language
+    /// before they are summarized
+    pub fn with_translator(mut self, translator: Box<dyn Translator>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+
+    /// reuses transcripts and chunk summaries from earlier runs, and stores new ones
+    pub fn with_cache(mut self, cache: SummaryCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// replaces all options
+    pub fn with_options(mut self, options: PipelineOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// `translate` runs each chunk through the translator before summarizing it
+    pub fn summarize_segments(&self, video_id: &str, segments: &[TranscriptSegment], translate: bool) -> Result<String> {
+        let run = Run { video_id, translate, progress: &|_| {} };
+        self.summarize_sentences(&run, &chunking::sentences_from_segments(segments))
+    }
+
+    // map: summarize every chunk. reduce: re-summarize the joined chunk summaries
+    // until they fit the target length or the depth limit is reached
+    fn summarize_sentences(&self, run: &Run, sentences: &[String]) -> Result<String> {
+        let mut summary = self.summarize_chunks(run, sentences)?;
+
+        // the chunk summaries are already in the target language, and progress
+        // within a reduce pass isn't worth reporting
+        let reduce = Run { translate: false, progress: &|_| {}, ..*run };
+
+        for depth in 1..=self.options.reduce_depth {
+            if summary.chars().count() <= self.options.summary_length {
+                break;
+            }
+
+            (run.progress)(Progress::Reducing { pass: depth, max_passes: self.options.reduce_depth });
+
+            let reduced = self.summarize_chunks(&reduce, &chunking::split_sentences(&summary))
+                .context(format!("Failed to reduce summaries (pass {})", depth))?;
+
+            // the model can't shorten it any further
+            if reduced.chars().count() >= summary.chars().count() {
+                break;
+            }
+            summary = reduced;
+        }
+
+        Ok(summary)
+    }
+
+    fn summarize_chunks(&self, run: &Run, sentences: &[String]) -> Result<String> {
+        let translator = self.translator.as_deref().filter(|_| run.translate);
+
+        let mut limit = self.summarizer.limits().max_input_tokens;
+        if let Some(translator) = translator {
+            // translations run longer than their source, so leave the summarizer some room
+            limit = (limit * 3 / 4).min(translator.max_input_tokens());
+        }
+        let chunker = Chunker {
+            tokenizer: self.tokenizer.as_ref(),
+            max_tokens: self.options.chunk_tokens.unwrap_or(limit).min(limit),
+            overlap_tokens: self.options.chunk_overlap,
+        };
+        let chunks = chunker.chunk(sentences);
+        
+        info!(
+            "Processing {} chunks with {} ({})...",
+            chunks.len(),
+            self.summarizer.name(),
+            self.summarizer.version()
+        );
+
+        let fingerprint = match translator {
+            Some(translator) => format!("{}|{}", self.summarizer.fingerprint(), translator.fingerprint()),
+            None => self.summarizer.fingerprint(),
+        };
+
+        // workers take the next chunk until none are left or one of them fails;
+        // results go into the chunk's own slot so the order is kept
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results: Mutex<Vec<Option<Result<String>>>> = Mutex::new(chunks.iter().map(|_| None).collect());
+        (run.progress)(Progress::Summarizing { done: 0, total: chunks.len() });
+
+        thread::scope(|scope| {
+            for _ in 0..self.options.workers.clamp(1, chunks.len().max(1)) {
+                scope.spawn(|| {
+                    while !failed.load(Ordering::SeqCst) {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(chunk) = chunks.get(i) else { break };
+
+                        let result = self.summarize_chunk(run, &fingerprint, chunk, i, chunks.len());
+                        if result.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        results.lock().unwrap()[i] = Some(result);
+
+                        let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+                        (run.progress)(Progress::Summarizing { done, total: chunks.len() });
+                    }
+                });
+            }
+        });
+
+        // the first failing chunk in order, or chunks the workers never got to
+        let summaries = results.into_inner().unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Result<Vec<_>>>()?;
+
+        if summaries.is_empty() {
+            return Err(anyhow::anyhow!("No summaries were generated"));
+        }
+
+        // Join all summaries with newlines between them
+        Ok(summaries.join("\n\n"))
+    }
+
+    // translates (when the run asks for it) and summarizes chunk `i` of `total`,
+    // going through the cache
+    fn summarize_chunk(&self, run: &Run, fingerprint: &str, chunk: &str, i: usize, total: usize) -> Result<String> {
+        let cached = self.cache.as_ref()
+            .and_then(|cache| cache.get_chunk(run.video_id, fingerprint, chunk));
+        if let Some(summary) = cached {
+            debug!("Chunk {}/{} already summarized, using cache", i + 1, total);
+            return Ok(summary);
+        }
+
+        let translated;
+        let text = match self.translator.as_deref().filter(|_| run.translate) {
+            Some(translator) => {
+                debug!("Translating chunk {}/{}...", i + 1, total);
+                translated = translator.translate_chunk(chunk)
+                    .context(format!("Failed to translate chunk {}/{}", i + 1, total))?;
+                &translated
+            }
+            None => chunk,
+        };
+
+        let summary = self.summarizer.summarize_chunk(text)
+            .context(format!("Failed to summarize chunk {}/{}", i + 1, total))?;
+
+        // store each chunk straight away so a later failure doesn't lose it. the
+        // summary is good even if the cache isn't, so that only costs a warning
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.put_chunk(run.video_id, fingerprint, chunk, &summary) {
+                warn!("Failed to cache summary of chunk {}/{}: {:#}", i + 1, total, e);
+            }
+        }
+        Ok(summary)
+    }
+
+    /// one summary per chapter marker, or per time window when the video has none
+    pub fn summarize_chapters(&self, video_id: &str, transcript: &Transcript, translate: bool) -> Result<Vec<ChapterSummary>> {
+        let run = Run { video_id, translate, progress: &|_| {} };
+        self.summarize_chapters_with(&run, transcript)
+    }
+
+    fn summarize_chapters_with(&self, run: &Run, transcript: &Transcript) -> Result<Vec<ChapterSummary>> {
+        let chapters = chapters::group_segments(
+            &transcript.segments,
+            &transcript.chapters,
+            self.options.chapter_window_secs,
+        );
+
+        info!("Summarizing {} chapters...", chapters.len());
+
+        chapters.iter()
+            .map(|chapter| {
+                let timestamp = chapters::format_timestamp(chapter.start);
+                info!("Chapter at {}...", timestamp);
+
+                let summary = self.summarize_sentences(run, &chunking::sentences_from_segments(chapter.segments))
+                    .context(format!("Failed to summarize chapter at {}", timestamp))?;
+
+                Ok(ChapterSummary {
+                    title: chapter.title.clone(),
+                    start: chapter.start,
+                    end: chapter.end,
+                    url: chapters::watch_url(run.video_id, chapter.start),
+                    summary,
+                })
+            })
+            .collect()
+    }
+
+    /// the transcript of a video, from the cache when it has one
+    pub fn fetch_transcript(&self, video_id: &str) -> Result<Transcript> {
+        let fingerprint = self.transcripts.fingerprint();
+        if let Some(transcript) = self.cache.as_ref().and_then(|cache| cache.get_transcript(video_id, &fingerprint)) {
+            info!("Using cached transcript for video ID: {}", video_id);
+            return Ok(transcript);
+        }
+
+        let transcript = self.transcripts.fetch_transcript(video_id)?;
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.put_transcript(video_id, &fingerprint, &transcript) {
+                warn!("Failed to cache transcript of {}: {:#}", video_id, e);
+            }
+        }
+        Ok(transcript)
+    }
+
+    // whether the transcript is in another language than the translator produces.
+    // without track information it's assumed to be
+    fn needs_translation(&self, track: Option<&TranscriptTrack>) -> bool {
+        let Some(translator) = &self.translator else { return false };
+        match track {
+            Some(track) => {
+                let language = track.translated_to.as_deref().unwrap_or(&track.language);
+                !translator::is_same_language(language, translator.target_language())
+            }
+            None => true,
+        }
+    }
+
+    /// fetches the transcript of the video `youtube_url` links to and summarizes it.
+    /// `youtube_url` is any link `VideoRef::parse` accepts, or a bare video id
+    pub fn process_video(&self, youtube_url: &str) -> Result<Summary> {
+        self.process_video_with_progress(youtube_url, &|_| {})
+    }
+
+    /// like `process_video`, calling `progress` as the run moves from one step to the next
+    pub fn process_video_with_progress(&self, youtube_url: &str, progress: &(dyn Fn(Progress) + Sync)) -> Result<Summary> {
+        let video_id = VideoRef::parse(youtube_url)?.id;
+        
+        debug!("Extracted video ID: {}", video_id);
+        
+        let mut result = Summary {
+            video_id: Some(video_id.clone()),
+            url: Some(youtube_url.trim().to_string()),
+            model: Some(format!("{} ({})", self.summarizer.name(), self.summarizer.version())),
+            transcript: None,
+            transcript_track: None,
+            translator: None,
+            summary: None,
+            chapters: None,
+        };
+
+        progress(Progress::FetchingTranscript { video_id: video_id.clone() });
+        let transcript = self.fetch_transcript(&video_id)?;
+        result.transcript_track = transcript.track.clone();
+
+        let translate = self.needs_translation(transcript.track.as_ref());
+        if let (true, Some(translator)) = (translate, &self.translator) {
+            info!("Translating transcript to {} with {}", translator.target_language(), translator.name());
+            result.translator = Some(format!("{} ({})", translator.name(), translator.target_language()));
+        }
+
+        let run = Run { video_id: &video_id, translate, progress };
+        if self.options.chapters {
+            let chapters = self.summarize_chapters_with(&run, &transcript)?;
+            let summary = chapters.iter()
+                .map(|c| c.summary.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            result.summary = Some(summary);
+            result.chapters = Some(chapters);
+        } else {
+            let summary = self.summarize_sentences(&run, &chunking::sentences_from_segments(&transcript.segments))?;
+            result.summary = Some(summary);
+        }
+
+        result.transcript = Some(transcript.segments);
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::WordTokenizer;
+    use crate::error::{self, Error};
+    use crate::summarizer::SummarizerLimits;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const WORDS: [&str; 8] = ["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel"];
+
+    // summarizes a chunk to its first word, slowly for short words so parallel
+    // workers finish out of order. chunks mentioning "boom" fail unless `explode`
+    // is off
+    struct FakeSummarizer {
+        calls: Arc<Mutex<Vec<String>>>,
+        shrink: bool,
+        explode: bool,
+        version: &'static str,
+    }
+
+    impl FakeSummarizer {
+        fn new() -> Self {
+            FakeSummarizer { calls: Arc::new(Mutex::new(Vec::new())), shrink: true, explode: true, version: "1" }
+        }
+    }
+
+    impl Summarizer for FakeSummarizer {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn version(&self) -> &str {
+            self.version
+        }
+
+        fn limits(&self) -> SummarizerLimits {
+            SummarizerLimits { max_input_tokens: 4, min_summary_length: 1, max_summary_length: 10 }
+        }
+
+        fn summarize_chunk(&self, chunk: &str) -> Result<String> {
+            self.calls.lock().unwrap().push(chunk.to_string());
+            if self.explode && chunk.contains("boom") {
+                return Err(Error::ResponseParse("fake backend exploded".to_string()).into());
+            }
+            thread::sleep(Duration::from_millis(40u64.saturating_sub(chunk.len() as u64 * 2)));
+
+            if !self.shrink {
+                return Ok(chunk.to_string());
+            }
+            let first = chunk.split_whitespace().next().unwrap_or_default().trim_end_matches('.');
+            Ok(format!("{}.", first))
+        }
+    }
+
+    struct FakeSource(Vec<TranscriptSegment>);
+
+    impl TranscriptSource for FakeSource {
+        fn fetch_transcript(&self, _video_id: &str) -> Result<Transcript> {
+            Ok(Transcript { segments: self.0.clone(), chapters: Vec::new(), track: None })
+        }
+    }
+
+    // one four word sentence per word, so every sentence is a chunk of its own
+    fn segments(words: &[&str]) -> Vec<TranscriptSegment> {
+        words.iter()
+            .enumerate()
+            .map(|(i, word)| TranscriptSegment {
+                start: i as f64 * 5.0,
+                duration: 5.0,
+                text: format!("{} is a word.", word),
+            })
+            .collect()
+    }
+
+    fn pipeline(summarizer: FakeSummarizer, words: &[&str], options: PipelineOptions) -> VideoSummarizer {
+        VideoSummarizer::new(Box::new(summarizer), Box::new(FakeSource(segments(words))))
+            .with_tokenizer(Box::new(WordTokenizer))
+            .with_options(options)
+    }
+
+    fn no_reduce() -> PipelineOptions {
+        PipelineOptions { reduce_depth: 0, ..PipelineOptions::default() }
+    }
+
+    #[test]
+    fn keeps_chunk_order_with_parallel_workers() {
+        let summarizer = pipeline(FakeSummarizer::new(), &WORDS, PipelineOptions { workers: 4, ..no_reduce() });
+        let summary = summarizer.summarize_segments("video", &segments(&WORDS), false).unwrap();
+
+        let expected: Vec<String> = WORDS.iter().map(|word| format!("{}.", word)).collect();
+        assert_eq!(summary, expected.join("\n\n"));
+    }
+
+    #[test]
+    fn single_worker_gives_the_same_result() {
+        let options = |workers| PipelineOptions { workers, ..no_reduce() };
+        let sequential = pipeline(FakeSummarizer::new(), &WORDS, options(1))
+            .summarize_segments("video", &segments(&WORDS), false)
+            .unwrap();
+        let parallel = pipeline(FakeSummarizer::new(), &WORDS, options(8))
+            .summarize_segments("video", &segments(&WORDS), false)
+            .unwrap();
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn reduces_until_short_enough() {
+        let options = PipelineOptions { summary_length: 10, reduce_depth: 3, ..PipelineOptions::default() };
+        let summarizer = pipeline(FakeSummarizer::new(), &WORDS, options);
+
+        let events = Mutex::new(Vec::new());
+        let summary = summarizer
+            .process_video_with_progress("dQw4w9WgXcQ", &|progress| events.lock().unwrap().push(progress))
+            .unwrap();
+
+        // 8 chunk summaries, then 2 chunks of four, then one
+        assert_eq!(summary.summary.as_deref(), Some("alpha."));
+        let reduces: Vec<Progress> = events.into_inner().unwrap()
+            .into_iter()
+            .filter(|event| matches!(event, Progress::Reducing { .. }))
+            .collect();
+        assert_eq!(reduces, vec![
+            Progress::Reducing { pass: 1, max_passes: 3 },
+            Progress::Reducing { pass: 2, max_passes: 3 },
+        ]);
+    }
+
+    #[test]
+    fn summary_length_counts_characters_not_bytes() {
+        let words = ["größe", "übung"];
+        let options = PipelineOptions { summary_length: 14, reduce_depth: 1, ..PipelineOptions::default() };
+        let summary = pipeline(FakeSummarizer::new(), &words, options)
+            .summarize_segments("video", &segments(&words), false)
+            .unwrap();
+        // 14 characters but 17 bytes, so no reduce pass is needed
+        assert_eq!(summary, "größe.\n\nübung.");
+    }
+
+    #[test]
+    fn reduce_stops_at_the_depth_limit() {
+        let options = PipelineOptions { summary_length: 1, reduce_depth: 1, ..PipelineOptions::default() };
+        let summary = pipeline(FakeSummarizer::new(), &WORDS, options)
+            .summarize_segments("video", &segments(&WORDS), false)
+            .unwrap();
+        assert_eq!(summary, "alpha.\n\necho.");
+    }
+
+    #[test]
+    fn reduce_stops_when_the_model_cannot_shorten() {
+        let summarizer = FakeSummarizer { shrink: false, ..FakeSummarizer::new() };
+        let options = PipelineOptions { summary_length: 1, reduce_depth: 3, ..PipelineOptions::default() };
+        let summary = pipeline(summarizer, &WORDS[..2], options)
+            .summarize_segments("video", &segments(&WORDS[..2]), false)
+            .unwrap();
+        assert_eq!(summary, "alpha is a word.\n\nbravo is a word.");
+    }
+
+    #[test]
+    fn reports_chunk_progress_up_to_the_total() {
+        let summarizer = pipeline(FakeSummarizer::new(), &WORDS, PipelineOptions { workers: 3, ..no_reduce() });
+
+        let events = Mutex::new(Vec::new());
+        summarizer
+            .process_video_with_progress("dQw4w9WgXcQ", &|progress| events.lock().unwrap().push(progress))
+            .unwrap();
+
+        let events = events.into_inner().unwrap();
+        assert_eq!(events[0], Progress::FetchingTranscript { video_id: "dQw4w9WgXcQ".to_string() });
+        let done: Vec<usize> = events.iter()
+            .filter_map(|event| match event {
+                Progress::Summarizing { done, total: 8 } => Some(*done),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(done.len(), 9);
+        assert_eq!(done.iter().max(), Some(&8));
+    }
+
+    #[test]
+    fn chunk_failures_fail_the_video() {
+        let words = ["alpha", "bravo", "boom", "delta", "echo"];
+        let summarizer = pipeline(FakeSummarizer::new(), &words, PipelineOptions { workers: 2, ..no_reduce() });
+
+        let error = summarizer.process_video("dQw4w9WgXcQ").unwrap_err();
+        assert!(format!("{:#}", error).contains("Failed to summarize chunk 3/5"), "{:#}", error);
+        assert!(matches!(error::classify(&error), Some(Error::ResponseParse(_))));
+    }
+
+    #[test]
+    fn workers_stop_taking_chunks_after_a_failure() {
+        let words = ["boom", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel"];
+        let summarizer = FakeSummarizer::new();
+        let calls = Arc::clone(&summarizer.calls);
+        let pipeline = pipeline(summarizer, &words, PipelineOptions { workers: 1, ..no_reduce() });
+
+        assert!(pipeline.summarize_segments("video", &segments(&words), false).is_err());
+        assert_eq!(*calls.lock().unwrap(), vec!["boom is a word.".to_string()]);
+    }
+
+    // runs `summarizer` over `words` with a cache in `dir`, returning the chunks
+    // it was asked to summarize
+    fn cached_run(dir: &std::path::Path, summarizer: FakeSummarizer, words: &[&str]) -> (Result<Summary>, Vec<String>) {
+        let calls = Arc::clone(&summarizer.calls);
+        let result = pipeline(summarizer, words, PipelineOptions { workers: 1, ..no_reduce() })
+            .with_cache(SummaryCache::new(dir))
+            .process_video("dQw4w9WgXcQ");
+        let calls = calls.lock().unwrap().clone();
+        (result, calls)
+    }
+
+    fn cache_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("pipeline-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn finished_videos_are_served_from_the_cache() {
+        let dir = cache_dir("finished");
+        let (first, first_calls) = cached_run(&dir, FakeSummarizer::new(), &WORDS);
+        let (second, second_calls) = cached_run(&dir, FakeSummarizer::new(), &WORDS);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first_calls.len(), 8);
+        assert!(second_calls.is_empty(), "{:?}", second_calls);
+        assert_eq!(first.unwrap().summary, second.unwrap().summary);
+    }
+
+    #[test]
+    fn failed_runs_resume_with_the_missing_chunks() {
+        let dir = cache_dir("resume");
+        let words = ["alpha", "bravo", "boom", "delta"];
+        let (failed, _) = cached_run(&dir, FakeSummarizer::new(), &words);
+        let (resumed, calls) = cached_run(&dir, FakeSummarizer { explode: false, ..FakeSummarizer::new() }, &words);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(failed.is_err());
+        assert_eq!(calls, ["boom is a word.", "delta is a word."]);
+        assert_eq!(resumed.unwrap().summary.as_deref(), Some("alpha.\n\nbravo.\n\nboom.\n\ndelta."));
+    }
+
+    #[test]
+    fn another_fingerprint_misses_the_cache() {
+        let dir = cache_dir("fingerprint");
+        cached_run(&dir, FakeSummarizer::new(), &WORDS).0.unwrap();
+        let (_, calls) = cached_run(&dir, FakeSummarizer { version: "2", ..FakeSummarizer::new() }, &WORDS);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(calls.len(), 8);
+    }
+
+    #[test]
+    fn invalid_urls_are_rejected_before_fetching() {
+        let error = pipeline(FakeSummarizer::new(), &WORDS, no_reduce())
+            .process_video("https://example.com/watch?v=dQw4w9WgXcQ")
+            .unwrap_err();
+        assert!(matches!(error::classify(&error), Some(Error::InvalidUrl(_))));
+    }
+
+    struct GermanSource;
+
+    impl TranscriptSource for GermanSource {
+        fn fetch_transcript(&self, _video_id: &str) -> Result<Transcript> {
+            let track = TranscriptTrack { language: "de".to_string(), name: None, generated: false, translated_to: None };
+            Ok(Transcript { segments: segments(&["hallo"]), chapters: Vec::new(), track: Some(track) })
+        }
+    }
+
+    struct FakeTranslator;
+
+    impl Translator for FakeTranslator {
+        fn name(&self) -> &str {
+            "fake-translator"
+        }
+
+        fn target_language(&self) -> &str {
+            "en"
+        }
+
+        fn max_input_tokens(&self) -> usize {
+            100
+        }
+
+        fn translate_chunk(&self, chunk: &str) -> Result<String> {
+            Ok(chunk.replace("hallo", "hello"))
+        }
+    }
+
+    #[test]
+    fn translation_is_recorded_as_the_translator_not_on_the_track() {
+        let summary = VideoSummarizer::new(Box::new(FakeSummarizer::new()), Box::new(GermanSource))
+            .with_tokenizer(Box::new(WordTokenizer))
+            .with_options(no_reduce())
+            .with_translator(Box::new(FakeTranslator))
+            .process_video("dQw4w9WgXcQ")
+            .unwrap();
+
+        assert!(summary.summary.unwrap().starts_with("hello."));
+        assert_eq!(summary.translator.as_deref(), Some("fake-translator (en)"));
+        let track = summary.transcript_track.unwrap();
+        assert_eq!((track.language.as_str(), track.translated_to), ("de", None));
+    }
+}
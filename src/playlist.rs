@@ -0,0 +1,203 @@
+//! Expanding playlist and channel links into the videos they contain.
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::transcript::{extract_page_json, USER_AGENT, YOUTUBE_BASE_URL};
+
+// upper bound on continuation requests, so a runaway channel can't loop forever
+const MAX_PAGES: usize = 100;
+
+/// what a batch input points at
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchInput {
+    /// a video link or id, as given
+    Video(String),
+    /// a playlist id
+    Playlist(String),
+    /// path of the channel page, e.g. "/@handle" or "/channel/UC..."
+    Channel(String),
+}
+
+impl BatchInput {
+    /// playlist and channel links are recognised, anything else is taken for a video
+    pub fn classify(url: &str) -> BatchInput {
+        let path = url_path(url);
+
+        if path.starts_with("/playlist") {
+            if let Some(list) = query_param(url, "list") {
+                return BatchInput::Playlist(list);
+            }
+        }
+
+        let is_channel = ["/@", "/channel/", "/c/", "/user/"]
+            .iter()
+            .any(|prefix| path.starts_with(prefix));
+        if is_channel {
+            // drop tabs like /videos or /featured, the uploads playlist covers them all
+            let re = Regex::new(r"^(/@[^/?#]+|/(?:channel|c|user)/[^/?#]+)").unwrap();
+            if let Some(cap) = re.captures(&path) {
+                return BatchInput::Channel(cap[1].to_string());
+            }
+        }
+
+        BatchInput::Video(url.to_string())
+    }
+}
+
+/// expands playlists and channel uploads into video ids via youtube's pages
+pub struct PlaylistResolver {
+    agent: ureq::Agent,
+    base_url: String,
+}
+
+impl PlaylistResolver {
+    /// a resolver for youtube.com
+    pub fn new() -> Self {
+        Self::with_base_url(YOUTUBE_BASE_URL)
+    }
+
+    /// point the resolver at another host, e.g. a local server replaying recorded pages
+    pub fn with_base_url(base_url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        PlaylistResolver {
+            agent,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// ids of the playlist's videos in playlist order, following continuation pages
+    pub fn playlist_video_ids(&self, playlist_id: &str) -> Result<Vec<String>> {
+        let url = format!("{}/playlist?list={}", self.base_url, playlist_id);
+        let page = self.get(&url)?;
+        let data = extract_page_json(&page, "ytInitialData")?;
+
+        let mut ids = Vec::new();
+        let mut continuation = None;
+        collect_playlist_items(&data, &mut ids, &mut continuation);
+
+        // the page only holds the first 100 videos, the rest come from the browse api
+        let api_key = ytcfg_value(&page, "INNERTUBE_API_KEY");
+        let client_version = ytcfg_value(&page, "INNERTUBE_CLIENT_VERSION");
+
+        let mut pages = 0;
+        while let (Some(token), Some(key), Some(version)) = (continuation.take(), &api_key, &client_version) {
+            pages += 1;
+            if pages > MAX_PAGES {
+                warn!("Stopping playlist {} after {} videos", playlist_id, ids.len());
+                break;
+            }
+
+            let response: Value = self.agent
+                .post(&format!("{}/youtubei/v1/browse?key={}", self.base_url, key))
+                .send_json(ureq::json!({
+                    "context": { "client": { "clientName": "WEB", "clientVersion": version } },
+                    "continuation": token,
+                }))
+                .context(format!("Failed to fetch more videos of playlist {}", playlist_id))?
+                .into_json()
+                .context("Failed to parse playlist continuation")?;
+
+            collect_playlist_items(&response, &mut ids, &mut continuation);
+        }
+
+        if ids.is_empty() {
+            return Err(anyhow!("Playlist {} has no videos or is private", playlist_id));
+        }
+
+        Ok(ids)
+    }
+
+    /// every channel has an uploads playlist: its id with the "UC" prefix swapped for "UU"
+    pub fn channel_video_ids(&self, channel_path: &str) -> Result<Vec<String>> {
+        let channel_id = match channel_path.strip_prefix("/channel/") {
+            Some(id) => id.to_string(),
+            None => {
+                let page = self.get(&format!("{}{}", self.base_url, channel_path))?;
+                let data = extract_page_json(&page, "ytInitialData")?;
+                data["metadata"]["channelMetadataRenderer"]["externalId"]
+                    .as_str()
+                    .map(String::from)
+                    .with_context(|| format!("Could not find channel id on {}", channel_path))?
+            }
+        };
+
+        let uploads = channel_id.strip_prefix("UC")
+            .map(|rest| format!("UU{}", rest))
+            .with_context(|| format!("Unexpected channel id {}", channel_id))?;
+
+        self.playlist_video_ids(&uploads)
+    }
+
+    fn get(&self, url: &str) -> Result<String> {
+        self.agent.get(url)
+            .set("Accept-Language", "en-US,en;q=0.9")
+            .set("Cookie", "CONSENT=YES+cb")
+            .call()
+            .context(format!("Failed to fetch {}", url))?
+            .into_string()
+            .context(format!("Failed to read response body from {}", url))
+    }
+}
+
+impl Default for PlaylistResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// walks initial data or a continuation response for video ids and the next page token
+fn collect_playlist_items(value: &Value, ids: &mut Vec<String>, continuation: &mut Option<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(id) = map.get("playlistVideoRenderer").and_then(|r| r["videoId"].as_str()) {
+                if !ids.iter().any(|existing| existing == id) {
+                    ids.push(id.to_string());
+                }
+            }
+            if let Some(token) = map.get("continuationItemRenderer")
+                .and_then(|r| r["continuationEndpoint"]["continuationCommand"]["token"].as_str())
+            {
+                *continuation = Some(token.to_string());
+            }
+            for child in map.values() {
+                collect_playlist_items(child, ids, continuation);
+            }
+        }
+        Value::Array(items) => {
+            for child in items {
+                collect_playlist_items(child, ids, continuation);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn ytcfg_value(page: &str, key: &str) -> Option<String> {
+    let re = Regex::new(&format!(r#""{}"\s*:\s*"([^"]+)""#, key)).unwrap();
+    re.captures(page).map(|cap| cap[1].to_string())
+}
+
+fn url_path(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    match without_scheme.find('/') {
+        Some(i) => without_scheme[i..].to_string(),
+        None => String::new(),
+    }
+}
+
+fn query_param(url: &str, name: &str) -> Option<String> {
+    let query = url.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or_default();
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
@@ -0,0 +1,112 @@
+//! Spacing out requests to stay within an API quota.
+
+use anyhow::{anyhow, Result};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// token bucket shared by all requests to one backend: holds up to `burst`
+/// tokens, refills at a steady rate, and every request takes a token,
+/// waiting for one if the bucket is empty
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// fails unless `per_second` is positive and finite. the bucket starts full,
+    /// so the first `burst` requests go out straight away
+    pub fn new(per_second: f64, burst: u32) -> Result<Self> {
+        if !(per_second > 0.0 && per_second.is_finite()) {
+            return Err(anyhow!("Rate limit must be a positive number of requests per second, got {}", per_second));
+        }
+
+        let burst = f64::from(burst.max(1));
+        Ok(RateLimiter {
+            per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+            }),
+        })
+    }
+
+    /// quotas are usually given per minute
+    pub fn per_minute(requests: f64, burst: u32) -> Result<Self> {
+        if !(requests > 0.0 && requests.is_finite()) {
+            return Err(anyhow!("Rate limit must be a positive number of requests per minute, got {}", requests));
+        }
+        Self::new(requests / 60.0, burst)
+    }
+
+    /// blocks until the request may be sent
+    pub fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+                bucket.refilled_at = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second)
+            };
+            // sleep without the lock so other threads can refill and compete fairly
+            thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_rates_that_are_not_positive() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(RateLimiter::new(rate, 1).is_err(), "{}", rate);
+            assert!(RateLimiter::per_minute(rate, 1).is_err(), "{}", rate);
+        }
+        let error = RateLimiter::per_minute(0.0, 1).unwrap_err();
+        assert!(error.to_string().contains("per minute, got 0"), "{:#}", error);
+    }
+
+    #[test]
+    fn burst_goes_out_at_once_then_requests_wait_their_turn() {
+        let limiter = RateLimiter::new(20.0, 3).unwrap();
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire();
+        }
+        let burst = started.elapsed();
+        limiter.acquire();
+        let next = started.elapsed();
+
+        assert!(burst < Duration::from_millis(20), "{:?}", burst);
+        // one token refills every 50ms
+        assert!(next >= Duration::from_millis(45), "{:?}", next);
+        assert!(next < Duration::from_millis(500), "{:?}", next);
+    }
+
+    #[test]
+    fn a_burst_of_zero_still_lets_one_request_through() {
+        let limiter = RateLimiter::new(1.0, 0).unwrap();
+        let started = Instant::now();
+        limiter.acquire();
+        assert!(started.elapsed() < Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,235 @@
+//! Retrying API requests that failed for reasons worth waiting out.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::error::Error;
+use crate::rate_limit::RateLimiter;
+
+/// how often and how patiently a failed API request is retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// retries after the first attempt before giving up
+    pub max_retries: u32,
+    /// wait before the first retry, doubled for every one after it
+    pub base_delay: Duration,
+    /// longest backoff between two attempts. a server asking for a longer wait
+    /// gets it, up to `max_server_delay`
+    pub max_delay: Duration,
+    /// longest wait a server may ask for via Retry-After or `estimated_time`. when
+    /// it asks for more, the request fails right away instead of hanging
+    pub max_server_delay: Duration,
+    /// every attempt, retries included, waits for its turn here first
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(120),
+            max_server_delay: Duration::from_secs(600),
+            rate_limiter: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// copies of the policy share the limiter, so give each backend its own
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+        self
+    }
+
+    /// exponential backoff with jitter: half the delay is fixed, the other half random,
+    /// so parallel clients don't all come back at the same moment
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        let capped = exp.min(self.max_delay);
+        capped.mul_f64(0.5 + 0.5 * random_fraction())
+    }
+
+    /// posts a json body, retrying rate limits, server errors and dropped connections.
+    /// waits for at least as long as the server asks via Retry-After or, for models
+    /// that are still loading, the `estimated_time` in the response body, and gives
+    /// up if that is more than `max_server_delay`
+    pub fn send_json(&self, request: impl Fn() -> ureq::Request, body: &Value) -> Result<ureq::Response> {
+        let mut attempt = 0;
+
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire();
+            }
+
+            let (reason, hint) = match request().send_json(body) {
+                Ok(response) => return Ok(response),
+                Err(ureq::Error::Status(code, response)) => {
+                    let retry_after = response.header("Retry-After").and_then(parse_retry_after);
+                    let body = response.into_string().unwrap_or_default();
+                    let (message, estimated) = parse_error_body(&body);
+
+                    if !is_retryable(code) || attempt >= self.max_retries {
+                        return Err(status_error(code, message, retry_after, estimated));
+                    }
+                    let hint = retry_after.or(estimated);
+                    if let Some(hint) = hint.filter(|hint| *hint > self.max_server_delay) {
+                        return Err(status_error(code, message, retry_after, estimated).context(format!(
+                            "Not retrying: the server asked to wait {:.0}s, longer than the {:.0}s limit",
+                            hint.as_secs_f64(),
+                            self.max_server_delay.as_secs_f64()
+                        )));
+                    }
+
+                    (format!("status code {}: {}", code, message), hint)
+                }
+                Err(ureq::Error::Transport(transport)) => {
+                    if attempt >= self.max_retries {
+                        return Err(anyhow!(transport));
+                    }
+                    (transport.to_string(), None)
+                }
+            };
+
+            // the server knows how long it needs, so its hint isn't capped by max_delay
+            let delay = match hint {
+                Some(hint) => hint.max(self.backoff(attempt)),
+                None => self.backoff(attempt),
+            };
+
+            attempt += 1;
+            warn!(
+                "Request failed ({}), retrying in {:.1}s (attempt {}/{})...",
+                reason,
+                delay.as_secs_f64(),
+                attempt,
+                self.max_retries
+            );
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// rate limits and server-side failures are worth another try, client errors are not
+pub fn is_retryable(status: u16) -> bool {
+    status == 408 || status == 429 || (500..600).contains(&status)
+}
+
+// typed errors for the failures callers act on, a plain one for the rest
+fn status_error(code: u16, message: String, retry_after: Option<Duration>, estimated: Option<Duration>) -> anyhow::Error {
+    let message = format!("status code {}: {}", code, message);
+    match code {
+        401 | 403 => Error::Auth(format!("API rejected the access token: {}", message)).into(),
+        429 => Error::RateLimited { message, retry_after }.into(),
+        // hugging face answers 503 with an estimated_time while the model loads
+        503 if estimated.is_some() || message.to_lowercase().contains("loading") => {
+            Error::ModelLoading { message, estimated_time: estimated }.into()
+        }
+        503 => anyhow!("Model is unavailable: {}", message),
+        _ => anyhow!("API request failed: {}", message),
+    }
+}
+
+// Retry-After is either a number of seconds or an http date; we only honour the former
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<f64>().ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+// hugging face reports errors as {"error": "...", "estimated_time": 20.0}
+fn parse_error_body(body: &str) -> (String, Option<Duration>) {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return (body.trim().to_string(), None);
+    };
+
+    let message = match &value["error"] {
+        Value::String(s) => s.clone(),
+        Value::Null => body.trim().to_string(),
+        other => other.to_string(),
+    };
+    let estimated = value["estimated_time"].as_f64()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64);
+
+    (message, estimated)
+}
+
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    hasher.write_u128(nanos);
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error;
+    use serde_json::json;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    // answers every request with 429 and the given Retry-After, then a 200 once
+    // `failures` have been sent
+    fn rate_limited_server(retry_after: &str, failures: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let retry_after = retry_after.to_string();
+
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else { break };
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf);
+                let response = if i < failures {
+                    format!("HTTP/1.1 429 Too Many Requests\r\nRetry-After: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", retry_after)
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}".to_string()
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        url
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+            max_server_delay: Duration::from_secs(1),
+            rate_limiter: None,
+        }
+    }
+
+    #[test]
+    fn waits_as_long_as_the_server_asks_beyond_max_delay() {
+        let url = rate_limited_server("0.3", 1);
+        let started = Instant::now();
+
+        policy().send_json(|| ureq::post(&url), &json!({})).unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(300), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn fails_when_the_server_asks_for_more_than_max_server_delay() {
+        let url = rate_limited_server("3600", 1);
+        let started = Instant::now();
+
+        let error = policy().send_json(|| ureq::post(&url), &json!({})).unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(format!("{:#}", error).contains("longer than the 1s limit"), "{:#}", error);
+        assert!(matches!(error::classify(&error), Some(Error::RateLimited { .. })));
+    }
+}
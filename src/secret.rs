@@ -0,0 +1,144 @@
+//! API tokens and where they are read from.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::warn;
+
+/// an api token or key. Debug never shows the value, so configs and backends can
+/// be logged or printed while debugging without leaking it
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    /// wraps a token or key
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// the actual value, for the one place it's needed: the request header
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([redacted])")
+    }
+}
+
+impl std::str::FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Secret::new(s))
+    }
+}
+
+/// runs a command such as `pass show hf` or `secret-tool lookup service huggingface`
+/// and takes the first line of its output as the secret
+pub fn from_command(command: &str) -> Result<Secret> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context(format!("Failed to run token command `{}`", command))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Token command `{}` failed ({})", command, output.status));
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .context(format!("Token command `{}` printed invalid UTF-8", command))?;
+    first_line(&stdout)
+        .with_context(|| format!("Token command `{}` printed nothing", command))
+}
+
+/// reads the first line of a file, warning when other users can read it
+pub fn from_file(path: &Path) -> Result<Secret> {
+    warn_if_world_readable(path);
+
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read token file {}", path.display()))?;
+    first_line(&content)
+        .with_context(|| format!("Token file {} is empty", path.display()))
+}
+
+/// files holding secrets should be private to their owner (chmod 600)
+pub fn warn_if_world_readable(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            if metadata.permissions().mode() & 0o004 != 0 {
+                warn!(
+                    "{} holds a secret but is readable by every user on this machine; run `chmod 600 {}`",
+                    path.display(),
+                    path.display()
+                );
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+fn first_line(text: &str) -> Option<Secret> {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(Secret::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn debug_never_shows_the_value() {
+        let secret = Secret::new("hf_abcdef123456");
+        assert_eq!(format!("{:?}", secret), "Secret([redacted])");
+        assert_eq!(format!("{:?}", Some(secret.clone())), "Some(Secret([redacted]))");
+        assert_eq!(secret.expose(), "hf_abcdef123456");
+    }
+
+    #[test]
+    fn command_output_gives_the_first_non_empty_line() {
+        let secret = from_command("printf '\\n  hf_from_command  \\nsecond line\\n'").unwrap();
+        assert_eq!(secret.expose(), "hf_from_command");
+    }
+
+    #[test]
+    fn command_without_output_or_failing_is_an_error() {
+        let error = from_command("printf '\\n  \\n'").unwrap_err();
+        assert!(error.to_string().contains("printed nothing"), "{:#}", error);
+
+        let error = from_command("echo hf_ignored; exit 3").unwrap_err();
+        assert!(error.to_string().contains("failed"), "{:#}", error);
+    }
+
+    #[test]
+    fn file_gives_the_first_non_empty_line() {
+        let path = env::temp_dir().join(format!("secret-file-{}", std::process::id()));
+
+        fs::write(&path, "\n  hf_from_file\nsecond line\n").unwrap();
+        let secret = from_file(&path).map(|secret| secret.expose().to_string());
+
+        fs::write(&path, " \n\n").unwrap();
+        let empty = from_file(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(secret.unwrap(), "hf_from_file");
+        assert!(empty.to_string().contains("is empty"), "{:#}", empty);
+        assert!(from_file(&path).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! A small REST API offering summaries over HTTP.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::error::{self, Error};
+use crate::job::{self, JobState, JobStore};
+use crate::pipeline::VideoSummarizer;
+use crate::video_ref::VideoRef;
+
+// requests larger than this are refused; a url fits many times over
+const MAX_BODY_BYTES: usize = 64 * 1024;
+// longest request line or header line, and most header lines, accepted
+const MAX_LINE_BYTES: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+// connections handled at the same time; more wait to be accepted
+const CONNECTION_THREADS: usize = 16;
+
+/// summarization as a small REST api:
+///
+/// - `POST /summaries` with `{"url": "..."}` queues a video and returns its job
+/// - `GET /summaries/{id}` returns the job's state and, once done, the summary.
+///   jobs aren't listed, so only whoever holds a job's id can read it
+/// - `GET /transcripts/{video_id}` returns a video's transcript
+///
+/// every request shares one pipeline, so its cache and token serve everyone.
+/// jobs live in a [`JobStore`], so they outlast the server
+#[derive(Clone)]
+pub struct Server {
+    summarizer: Arc<VideoSummarizer>,
+    store: Arc<JobStore>,
+    queue: Sender<String>,
+}
+
+impl Server {
+    /// starts `workers` threads that summarize queued videos one at a time each.
+    /// jobs a previous server left unfinished are queued again
+    pub fn new(summarizer: VideoSummarizer, store: JobStore, workers: usize) -> Result<Self> {
+        let (queue, pending) = mpsc::channel();
+        let server = Server {
+            summarizer: Arc::new(summarizer),
+            store: Arc::new(store),
+            queue,
+        };
+
+        for job in server.store.list()? {
+            if !job.state.is_finished() {
+                server.store.set_state(&job.id, JobState::Queued)?;
+                server.queue.send(job.id)?;
+            }
+        }
+
+        let pending = Arc::new(Mutex::new(pending));
+        for _ in 0..workers.max(1) {
+            let server = server.clone();
+            let pending = Arc::clone(&pending);
+            thread::spawn(move || server.work(&pending));
+        }
+
+        Ok(server)
+    }
+
+    /// accepts connections on `addr` (e.g. "127.0.0.1:8000") until the process exits.
+    /// a fixed number of threads handle them; while all are busy, new connections
+    /// wait in the listen backlog
+    pub fn serve(&self, addr: &str) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .context(format!("Failed to listen on {}", addr))?;
+        info!("Listening on http://{}", listener.local_addr()?);
+
+        let (connections, accepted) = mpsc::sync_channel(0);
+        let accepted = Arc::new(Mutex::new(accepted));
+        for _ in 0..CONNECTION_THREADS {
+            let server = self.clone();
+            let accepted = Arc::clone(&accepted);
+            thread::spawn(move || server.handle_connections(&accepted));
+        }
+
+        for stream in listener.incoming() {
+            match stream {
+                // blocks until a thread is free to take it
+                Ok(stream) => connections.send(stream)?,
+                Err(e) => error!("Failed to accept connection: {}", e),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_connections(&self, accepted: &Mutex<Receiver<TcpStream>>) {
+        loop {
+            let next = accepted.lock().unwrap().recv();
+            let Ok(stream) = next else { break };
+
+            if let Err(e) = self.handle_connection(stream) {
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    fn work(&self, pending: &Mutex<Receiver<String>>) {
+        loop {
+            // only hold the lock while waiting, not while summarizing
+            let next = pending.lock().unwrap().recv();
+            let Ok(id) = next else { break };
+
+            // the outcome is recorded in the job, where the client looks for it
+            if let Err(e) = job::run_job(&self.summarizer, &self.store, &id) {
+                error!("job {}: {:#}", id, e);
+            }
+        }
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+
+        let (status, body) = match read_request(&stream) {
+            Ok(request) => self.route(&request),
+            Err(e) => error_response(400, &format!("{:#}", e)),
+        };
+
+        let body = serde_json::to_string_pretty(&body)?;
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason_phrase(status),
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn route(&self, request: &Request) -> (u16, Value) {
+        let path = request.path.split('?').next().unwrap_or_default().trim_end_matches('/');
+        let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+
+        match (request.method.as_str(), parts.as_slice()) {
+            ("POST", ["summaries"]) => self.create_job(&request.body),
+            ("GET", ["summaries", id]) => match self.store.get(id) {
+                Some(job) => (200, json!(job)),
+                None => error_response(404, &format!("No job with id {}", id)),
+            },
+            ("GET", ["transcripts", video_id]) => {
+                let transcript = VideoRef::parse(video_id)
+                    .and_then(|video| self.summarizer.fetch_transcript(&video.id));
+                match transcript {
+                    Ok(transcript) => (200, json!(transcript)),
+                    Err(e) => failure_response(&e),
+                }
+            }
+            (_, ["summaries"]) | (_, ["summaries", _]) | (_, ["transcripts", _]) => {
+                error_response(405, &format!("{} is not allowed on {}", request.method, path))
+            }
+            _ => error_response(404, &format!("No route for {}", path)),
+        }
+    }
+
+    fn create_job(&self, body: &[u8]) -> (u16, Value) {
+        let url = match serde_json::from_slice::<Value>(body) {
+            Ok(value) => value["url"].as_str().map(str::to_string),
+            Err(_) => None,
+        };
+        let Some(url) = url else {
+            return error_response(400, "Expected a JSON body like {\"url\": \"https://www.youtube.com/watch?v=...\"}");
+        };
+        if let Err(e) = VideoRef::parse(&url) {
+            return failure_response(&e);
+        }
+
+        let job = match self.store.create(&url) {
+            Ok(job) => job,
+            Err(e) => return failure_response(&e),
+        };
+        if self.queue.send(job.id.clone()).is_err() {
+            return error_response(503, "The server is shutting down");
+        }
+        (202, json!(job))
+    }
+}
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+fn read_request(stream: impl Read) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    read_line(&mut reader, &mut line).context("Failed to read request line")?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(anyhow!("Malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut content_length = 0;
+    let mut headers = 0;
+    loop {
+        if read_line(&mut reader, &mut line).context("Failed to read headers")? == 0 || line.trim().is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(anyhow!("More than {} headers", MAX_HEADERS));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().context("Invalid Content-Length")?;
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(anyhow!("Request body is larger than {} bytes", MAX_BODY_BYTES));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).context("Failed to read request body")?;
+
+    Ok(Request { method, path, body })
+}
+
+// replaces `line` with the next line, refusing lines longer than MAX_LINE_BYTES
+// rather than buffering whatever the client sends
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<usize> {
+    line.clear();
+    let read = reader.by_ref().take(MAX_LINE_BYTES as u64).read_line(line)?;
+    if read == MAX_LINE_BYTES && !line.ends_with('\n') {
+        return Err(anyhow!("Line is longer than {} bytes", MAX_LINE_BYTES));
+    }
+    Ok(read)
+}
+
+// maps the failure categories onto http statuses
+fn failure_response(error: &anyhow::Error) -> (u16, Value) {
+    let kind = error::classify(error);
+    let status = match kind {
+        Some(Error::InvalidUrl(_)) => 400,
+        Some(Error::TranscriptUnavailable { .. } | Error::TranscriptsDisabled { .. } | Error::AgeRestricted { .. }) => 404,
+        Some(Error::RateLimited { .. }) => 429,
+        Some(Error::ModelLoading { .. }) => 503,
+        Some(Error::Auth(_) | Error::ResponseParse(_)) => 502,
+        None => 500,
+    };
+    (status, json!({ "error": format!("{:#}", error), "error_kind": kind.map(Error::kind) }))
+}
+
+fn error_response(status: u16, message: &str) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(raw: &str) -> Result<Request> {
+        read_request(raw.as_bytes())
+    }
+
+    #[test]
+    fn reads_method_path_and_body() {
+        let request = request("POST /summaries HTTP/1.1\r\nHost: x\r\nContent-Length: 9\r\n\r\n{\"url\":1}").unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/summaries"));
+        assert_eq!(request.body, b"{\"url\":1}");
+    }
+
+    #[test]
+    fn refuses_long_lines() {
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_BYTES));
+        assert!(format!("{:#}", request(&long_path).unwrap_err()).contains("Line is longer than"));
+
+        let long_header = format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_LINE_BYTES));
+        assert!(format!("{:#}", request(&long_header).unwrap_err()).contains("Line is longer than"));
+
+        let just_fits = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE_BYTES - 6));
+        assert!(request(&just_fits).is_ok());
+    }
+
+    #[test]
+    fn refuses_too_many_headers() {
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS);
+        assert!(request(&format!("GET / HTTP/1.1\r\n{}\r\n", headers)).is_ok());
+
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS + 1);
+        let error = request(&format!("GET / HTTP/1.1\r\n{}\r\n", headers)).unwrap_err();
+        assert!(error.to_string().contains("More than 100 headers"), "{:#}", error);
+    }
+
+    #[test]
+    fn refuses_large_bodies() {
+        let error = request(&format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1)).unwrap_err();
+        assert!(error.to_string().contains("larger than"), "{:#}", error);
+    }
+}
@@ -0,0 +1,38 @@
+//! The interface summarization backends implement.
+
+use anyhow::Result;
+
+/// input/output bounds a summarization backend works within
+#[derive(Debug, Clone, Copy)]
+pub struct SummarizerLimits {
+    /// longest chunk (in model tokens) the backend accepts in one request
+    pub max_input_tokens: usize,
+    /// shortest summary the backend is asked for, in its own units (tokens or words)
+    pub min_summary_length: usize,
+    /// longest summary the backend is asked for, in its own units (tokens or words)
+    pub max_summary_length: usize,
+}
+
+/// a backend that turns a single chunk of transcript text into a summary
+pub trait Summarizer: Send + Sync {
+    /// short identifier of the backend and model, e.g. "huggingface:facebook/bart-large-cnn"
+    fn name(&self) -> &str;
+
+    /// model revision or backend version, used to tell results of different models apart
+    fn version(&self) -> &str;
+
+    /// how large the chunks given to `summarize_chunk` may be, and how long its
+    /// summaries will be
+    fn limits(&self) -> SummarizerLimits;
+
+    /// summarizes `chunk`, which is at most `limits().max_input_tokens` long.
+    /// called from several threads at once when the pipeline has more than one
+    /// worker. failures worth telling apart, like rate limits, are `Error`s
+    fn summarize_chunk(&self, chunk: &str) -> Result<String>;
+
+    /// everything that affects the output for a given chunk, used as part of cache
+    /// keys. backends with extra generation parameters should include them
+    fn fingerprint(&self) -> String {
+        format!("{}|{}|{:?}", self.name(), self.version(), self.limits())
+    }
+}
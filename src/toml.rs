@@ -0,0 +1,612 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+// parses the subset of TOML that config files need into a json value:
+// tables and dotted keys, strings (basic, literal and multi-line), integers
+// (also hex, octal and binary), floats, booleans, arrays and inline tables.
+// dates, inf/nan and arrays of tables are not supported
+pub fn parse(input: &str) -> Result<Value> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+    parser.document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn document(&mut self) -> Result<Value> {
+        let mut root = Map::new();
+        let mut table: Vec<String> = Vec::new();
+        // tables with a [header], tables created by dotted keys, and inline
+        // tables: none of them may be defined again or extended elsewhere
+        let mut headers: HashSet<Vec<String>> = HashSet::new();
+        let mut dotted: HashSet<Vec<String>> = HashSet::new();
+        let mut inline: HashSet<Vec<String>> = HashSet::new();
+
+        loop {
+            self.skip_blank_lines();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.pos += 1;
+                    if self.peek() == Some('[') {
+                        return Err(self.error("arrays of tables are not supported"));
+                    }
+                    table = self.key()?;
+                    self.skip_spaces();
+                    self.expect(']')?;
+                    self.end_of_line()?;
+
+                    let name = table.join(".");
+                    if dotted.contains(&table) || !headers.insert(table.clone()) {
+                        return Err(self.error(&format!("table '{}' is defined more than once", name)));
+                    }
+                    self.check_not_inline(&inline, &table)?;
+                    self.table_at(&mut root, &table)?;
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.skip_spaces();
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    self.end_of_line()?;
+
+                    let (last, parents) = key.split_last().expect("keys are never empty");
+                    let path: Vec<String> = table.iter().chain(parents).cloned().collect();
+                    self.check_not_inline(&inline, &path)?;
+                    for depth in table.len() + 1..=path.len() {
+                        let prefix = path[..depth].to_vec();
+                        if headers.contains(&prefix) {
+                            return Err(self.error(&format!("table '{}' is defined more than once", prefix.join("."))));
+                        }
+                        dotted.insert(prefix);
+                    }
+
+                    let target = self.table_at(&mut root, &path)?;
+                    if target.contains_key(last) {
+                        return Err(self.error(&format!("duplicate key '{}'", last)));
+                    }
+                    if value.is_object() {
+                        inline.insert(path.iter().chain([last]).cloned().collect());
+                    }
+                    target.insert(last.clone(), value);
+                }
+            }
+        }
+
+        Ok(Value::Object(root))
+    }
+
+    fn check_not_inline(&self, inline: &HashSet<Vec<String>>, path: &[String]) -> Result<()> {
+        match (1..=path.len()).find(|depth| inline.contains(&path[..*depth])) {
+            Some(depth) => Err(self.error(&format!("inline table '{}' can't be extended", path[..depth].join(".")))),
+            None => Ok(()),
+        }
+    }
+
+    // walks (creating as needed) to the table at `path`
+    fn table_at<'a>(&self, root: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>> {
+        let mut current = root;
+        for part in path {
+            let entry = current.entry(part.clone()).or_insert_with(|| Value::Object(Map::new()));
+            current = match entry {
+                Value::Object(map) => map,
+                _ => return Err(self.error(&format!("'{}' is not a table", part))),
+            };
+        }
+        Ok(current)
+    }
+
+    // dotted key of bare or quoted parts
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            parts.push(part);
+
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('"') if self.starts_with("\"\"\"") => self.multiline_string('"').map(Value::String),
+            Some('\'') if self.starts_with("'''") => self.multiline_string('\'').map(Value::String),
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some('t') if self.starts_with("true") => {
+                self.pos += 4;
+                Ok(Value::Bool(true))
+            }
+            Some('f') if self.starts_with("false") => {
+                self.pos += 5;
+                Ok(Value::Bool(false))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(out),
+                Some('\\') => out.push(self.escape()?),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.expect('\'')?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('\'') => return Ok(out),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    // """...""" (with escapes) or '''...''' (without); a newline right after the
+    // opening quotes is dropped
+    fn multiline_string(&mut self, quote: char) -> Result<String> {
+        let delimiter = if quote == '"' { "\"\"\"" } else { "'''" };
+        self.pos += 3;
+        if self.peek() == Some('\n') {
+            self.pos += 1;
+        } else if self.starts_with("\r\n") {
+            self.pos += 2;
+        }
+
+        let mut out = String::new();
+        loop {
+            if self.starts_with(delimiter) {
+                // up to two quotes may come right before the closing delimiter
+                let mut quotes = 0;
+                while self.chars.get(self.pos + quotes) == Some(&quote) {
+                    quotes += 1;
+                }
+                if quotes > 5 {
+                    return Err(self.error("too many quotes at the end of a multi-line string"));
+                }
+                out.extend(std::iter::repeat(quote).take(quotes - 3));
+                self.pos += quotes;
+                return Ok(out);
+            }
+            match self.next() {
+                None => return Err(self.error("unterminated multi-line string")),
+                Some('\\') if quote == '"' => {
+                    // a backslash at the end of a line trims the line break and leading whitespace
+                    let mut end = self.pos;
+                    while self.chars.get(end).is_some_and(|c| *c == ' ' || *c == '\t') {
+                        end += 1;
+                    }
+                    if matches!(self.chars.get(end), Some('\n') | Some('\r')) {
+                        while self.peek().is_some_and(char::is_whitespace) {
+                            self.pos += 1;
+                        }
+                    } else {
+                        out.push(self.escape()?);
+                    }
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char> {
+        match self.next() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some(kind @ ('u' | 'U')) => {
+                let len = if kind == 'u' { 4 } else { 8 };
+                let hex: String = (0..len).filter_map(|_| self.next()).collect();
+                u32::from_str_radix(&hex, 16).ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error(&format!("invalid unicode escape '\\{}{}'", kind, hex)))
+            }
+            _ => Err(self.error("invalid escape sequence")),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank_lines();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut map = Map::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(map));
+        }
+        loop {
+            let key = self.key()?;
+            self.skip_spaces();
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+
+            let (last, parents) = key.split_last().expect("keys are never empty");
+            let target = self.table_at(&mut map, parents)?;
+            if target.contains_key(last) {
+                return Err(self.error(&format!("duplicate key '{}'", last)));
+            }
+            target.insert(last.clone(), value);
+
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(map)),
+                _ => return Err(self.error("expected ',' or '}' in inline table")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_')) {
+            self.pos += 1;
+        }
+        let raw: String = self.chars[start..self.pos].iter().collect();
+        let invalid = || self.error(&format!("invalid number '{}'", raw));
+
+        // underscores only between digits
+        let digits: Vec<char> = raw.chars().collect();
+        let misplaced_underscore = digits.iter().enumerate().any(|(i, c)| {
+            *c == '_' && !(i > 0 && digits[i - 1].is_ascii_alphanumeric() && digits.get(i + 1).is_some_and(char::is_ascii_alphanumeric))
+        });
+        if misplaced_underscore {
+            return Err(invalid());
+        }
+        let text = raw.replace('_', "");
+
+        for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+            if let Some(digits) = text.strip_prefix(prefix) {
+                return i64::from_str_radix(digits, radix).map(Value::from).map_err(|_| invalid());
+            }
+        }
+
+        // no leading zeros, and a dot needs digits on both sides
+        let unsigned = text.trim_start_matches(['+', '-']);
+        let leading_zero = unsigned.len() > 1 && unsigned.starts_with('0') && unsigned.as_bytes()[1].is_ascii_digit();
+        let bare_dot = unsigned.split(['e', 'E']).next().is_some_and(|mantissa| {
+            mantissa.starts_with('.') || mantissa.ends_with('.')
+        });
+        if leading_zero || bare_dot || !unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        if let Ok(n) = text.parse::<i64>() {
+            return Ok(Value::from(n));
+        }
+        text.parse::<f64>().ok()
+            .filter(|n| n.is_finite())
+            .map(Value::from)
+            .ok_or_else(invalid)
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_spaces();
+        match self.peek() {
+            None | Some('\n') | Some('#') => Ok(()),
+            Some('\r') if self.starts_with("\r\n") => Ok(()),
+            Some(c) => Err(self.error(&format!("unexpected '{}' after value", c))),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+            self.pos += 1;
+        }
+    }
+
+    // whitespace, line breaks and comments
+    fn skip_blank_lines(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += 1,
+                Some('#') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", expected))),
+        }
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|c| **c == '\n').count() + 1;
+        anyhow!("line {}: {}", line, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ok(input: &str) -> Value {
+        parse(input).unwrap_or_else(|e| panic!("{:?}: {:#}", input, e))
+    }
+
+    fn err(input: &str) -> String {
+        match parse(input) {
+            Ok(value) => panic!("{:?} parsed as {}", input, value),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn config_file() {
+        let input = r#"
+# defaults
+backend = "openai"   # trailing comment
+model = 'llama3.1:8b'
+max_length = 150
+temperature = 0.7
+do_sample = true
+languages = ["de", "en"]
+
+[profiles.work]
+endpoint = "https://llm.example.com/v1"
+chunk_tokens = 1_024
+
+[profiles."local-llm"]
+backend = "huggingface"
+"#;
+        assert_eq!(ok(input), json!({
+            "backend": "openai",
+            "model": "llama3.1:8b",
+            "max_length": 150,
+            "temperature": 0.7,
+            "do_sample": true,
+            "languages": ["de", "en"],
+            "profiles": {
+                "work": { "endpoint": "https://llm.example.com/v1", "chunk_tokens": 1024 },
+                "local-llm": { "backend": "huggingface" },
+            },
+        }));
+    }
+
+    #[test]
+    fn empty_documents() {
+        assert_eq!(ok(""), json!({}));
+        assert_eq!(ok("\n  # only a comment\n\n"), json!({}));
+        assert_eq!(ok("[empty]"), json!({ "empty": {} }));
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(ok("a.b.c = 1\na.d = 2"), json!({ "a": { "b": { "c": 1 }, "d": 2 } }));
+        assert_eq!(ok("a . b = 1"), json!({ "a": { "b": 1 } }));
+        assert_eq!(ok(r#""quoted key" = 1"#), json!({ "quoted key": 1 }));
+        assert_eq!(ok(r#"'lit.eral' = 1"#), json!({ "lit.eral": 1 }));
+        assert_eq!(ok(r#""" = 1"#), json!({ "": 1 }));
+        assert_eq!(ok("bare-key_2 = 1"), json!({ "bare-key_2": 1 }));
+        assert_eq!(ok("1234 = 1"), json!({ "1234": 1 }));
+        assert_eq!(ok("[a]\nb.c = 1"), json!({ "a": { "b": { "c": 1 } } }));
+    }
+
+    #[test]
+    fn basic_string_escapes() {
+        assert_eq!(
+            ok(r#"s = "tab\tnl\ncr\rbs\bff\fq\"bsl\\u\u00e9U\U0001F600""#),
+            json!({ "s": "tab\tnl\ncr\rbs\u{8}ff\u{c}q\"bsl\\u\u{e9}U\u{1F600}" })
+        );
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        assert!(err(r#"s = "\x41""#).contains("invalid escape"));
+        assert!(err(r#"s = "\u12""#).contains("invalid unicode escape"));
+        assert!(err(r#"s = "\uD800""#).contains("invalid unicode escape"));
+        assert!(err(r#"s = "\ ""#).contains("invalid escape"));
+    }
+
+    #[test]
+    fn literal_strings_keep_backslashes() {
+        assert_eq!(ok(r"path = 'C:\Users\me'"), json!({ "path": r"C:\Users\me" }));
+    }
+
+    #[test]
+    fn multi_line_strings() {
+        assert_eq!(ok("s = \"\"\"\nfirst\nsecond\"\"\""), json!({ "s": "first\nsecond" }));
+        assert_eq!(ok("s = \"\"\"\r\nfirst\r\n\"\"\""), json!({ "s": "first\r\n" }));
+        assert_eq!(ok("s = '''\nraw \\n text'''"), json!({ "s": "raw \\n text" }));
+        assert_eq!(ok("s = \"\"\"one \\\n    two \\  \n  three\"\"\""), json!({ "s": "one two three" }));
+        assert_eq!(ok("s = \"\"\"tab\\there\"\"\""), json!({ "s": "tab\there" }));
+        // quotes inside and right before the closing delimiter
+        assert_eq!(ok(r#"s = """say "hi"""""#), json!({ "s": r#"say "hi""# }));
+        assert_eq!(ok("s = '''it''s'''"), json!({ "s": "it''s" }));
+        assert_eq!(ok("s = ''''quoted''''"), json!({ "s": "'quoted'" }));
+    }
+
+    #[test]
+    fn unterminated_strings() {
+        assert!(err("s = \"open").contains("unterminated string"));
+        assert!(err("s = \"line\nbreak\"").contains("unterminated string"));
+        assert!(err("s = 'open").contains("unterminated string"));
+        assert!(err("s = \"\"\"never closed").contains("unterminated multi-line string"));
+        assert!(err("s = \"\"\"a\"\"\"\"\"\"").contains("too many quotes"));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(ok("a = 42\nb = -17\nc = +5\nd = 0\ne = 1_000_000"), json!({ "a": 42, "b": -17, "c": 5, "d": 0, "e": 1000000 }));
+        assert_eq!(ok("a = 0xff\nb = 0o17\nc = 0b1010\nd = 0xdead_beef"), json!({ "a": 255, "b": 15, "c": 10, "d": 3735928559u64 }));
+        assert_eq!(ok("a = 3.5\nb = -0.25\nc = 1e3\nd = 6.02E+2\ne = 1_0.5"), json!({ "a": 3.5, "b": -0.25, "c": 1000.0, "d": 602.0, "e": 10.5 }));
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        for input in ["a = 012", "a = 1.", "a = 1.e5", "a = 1_", "a = 1__0", "a = 0xZZ", "a = +", "a = 1.5.5", "a = 1e", "a = +inf", "a = 2024-01-01"] {
+            assert!(err(input).contains("invalid number"), "{}", input);
+        }
+        assert!(err("a = .5").contains("expected a value"));
+        assert!(err("a = _1").contains("expected a value"));
+        assert!(err("a = nan").contains("expected a value"));
+    }
+
+    #[test]
+    fn booleans_must_be_lowercase() {
+        assert_eq!(ok("a = true\nb = false"), json!({ "a": true, "b": false }));
+        assert!(err("a = True").contains("expected a value"));
+        assert!(err("a = truely").contains("unexpected 'l'"));
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(ok("a = []"), json!({ "a": [] }));
+        assert_eq!(ok("a = [1, 'two', [3], {x = 4}]"), json!({ "a": [1, "two", [3], { "x": 4 }] }));
+        assert_eq!(ok("a = [\n  1, # one\n  2,\n]"), json!({ "a": [1, 2] }));
+        assert!(err("a = [1 2]").contains("expected ',' or ']'"));
+        assert!(err("a = [1,").contains("expected a value"));
+        assert!(err("a = [,]").contains("expected a value"));
+    }
+
+    #[test]
+    fn inline_tables() {
+        assert_eq!(ok("t = {}"), json!({ "t": {} }));
+        assert_eq!(ok("t = { a = 1, b.c = 'x' }"), json!({ "t": { "a": 1, "b": { "c": "x" } } }));
+        assert!(err("t = { a = 1, a = 2 }").contains("duplicate key 'a'"));
+        assert!(err("t = { a = 1 b = 2 }").contains("expected ',' or '}'"));
+    }
+
+    #[test]
+    fn inline_tables_are_closed() {
+        assert!(err("t = { a = 1 }\nt.b = 2").contains("inline table 't' can't be extended"));
+        assert!(err("t = { a = 1 }\n[t]").contains("inline table 't' can't be extended"));
+        assert!(err("t = { a = 1 }\n[t.sub]").contains("inline table 't' can't be extended"));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert!(err("a = 1\na = 2").contains("duplicate key 'a'"));
+        assert!(err("a.b = 1\na.b = 2").contains("duplicate key 'b'"));
+        assert!(err("[t]\na = 1\n[u]\n[t.v]\n[t]").contains("table 't' is defined more than once"));
+        assert!(err("a = 1\na.b = 2").contains("'a' is not a table"));
+        assert!(err("a = 1\n[a]").contains("'a' is not a table"));
+    }
+
+    #[test]
+    fn duplicate_tables() {
+        assert!(err("[a]\nx = 1\n[a]\ny = 2").contains("table 'a' is defined more than once"));
+        assert!(err("[a.b]\n[a.b]").contains("table 'a.b' is defined more than once"));
+        // a table made by dotted keys can't get a header afterwards, and the other way round
+        assert!(err("a.b = 1\n[a]").contains("table 'a' is defined more than once"));
+        assert!(err("[t]\nx.y = 1\n[t.x]").contains("table 't.x' is defined more than once"));
+        assert!(err("[a.b]\nc = 1\n[a]\nb.d = 2").contains("table 'a.b' is defined more than once"));
+    }
+
+    #[test]
+    fn tables_may_be_filled_in_later() {
+        // implicitly created super-tables can still get a header once
+        assert_eq!(ok("[a.b]\nc = 1\n[a]\nd = 2"), json!({ "a": { "b": { "c": 1 }, "d": 2 } }));
+        // sub-tables of dotted tables may have headers
+        assert_eq!(ok("[fruit]\napple.color = 'red'\n[fruit.apple.texture]\nsmooth = true"), json!({
+            "fruit": { "apple": { "color": "red", "texture": { "smooth": true } } }
+        }));
+    }
+
+    #[test]
+    fn unsupported_syntax() {
+        assert!(err("[[items]]").contains("arrays of tables are not supported"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(err("a").contains("expected '='"));
+        assert!(err("a =").contains("expected a value"));
+        assert!(err("= 1").contains("expected a key"));
+        assert!(err("a = 1 b = 2").contains("unexpected 'b' after value"));
+        assert!(err("[a").contains("expected ']'"));
+        assert!(err("[a] x = 1").contains("unexpected 'x'"));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert!(err("a = 1\n\nb = \"open").starts_with("line 3:"));
+        assert!(err("# comment\na = 1\na = 2").starts_with("line 3:"));
+    }
+
+    #[test]
+    fn windows_line_endings() {
+        assert_eq!(ok("a = 1\r\n[t]\r\nb = 'x'\r\n"), json!({ "a": 1, "t": { "b": "x" } }));
+    }
+}
@@ -0,0 +1,612 @@
+//! Fetching captions from YouTube, and the transcript types the rest of the crate works with.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::chapters::{self, ChapterMarker};
+use crate::error::Error;
+use crate::translator;
+
+/// where transcripts, playlists and channels are fetched from
+pub const YOUTUBE_BASE_URL: &str = "https://www.youtube.com";
+/// a desktop browser's, since youtube serves other clients different pages
+pub const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Safari/537.36";
+
+/// a single caption line and where it sits in the video, times in seconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    /// when the line appears
+    pub start: f64,
+    /// how long it stays
+    pub duration: f64,
+    /// the line, with whitespace collapsed
+    pub text: String,
+}
+
+/// which caption track a transcript was taken from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptTrack {
+    /// language code as listed by youtube, e.g. "de" or "en-GB"
+    pub language: String,
+    /// display name, e.g. "German (auto-generated)"
+    pub name: Option<String>,
+    /// auto-generated (speech recognition) rather than uploaded captions
+    pub generated: bool,
+    /// language youtube machine translated the captions into, if it did
+    #[serde(default)]
+    pub translated_to: Option<String>,
+}
+
+/// everything fetched for a video: its captions and any chapter markers from the description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    /// the caption lines in order
+    pub segments: Vec<TranscriptSegment>,
+    /// chapters listed in the description, empty when it has none
+    pub chapters: Vec<ChapterMarker>,
+    /// the caption track used, when the source knows it
+    #[serde(default)]
+    pub track: Option<TranscriptTrack>,
+}
+
+/// anything that can produce the transcript of a video
+pub trait TranscriptSource: Send + Sync {
+    /// the transcript of the video with this 11 character id. failures worth
+    /// telling apart, like a video without captions, are `Error`s
+    fn fetch_transcript(&self, video_id: &str) -> Result<Transcript>;
+
+    /// identifies the settings that decide which track is fetched, so cached
+    /// transcripts are only reused for the same preferences
+    fn fingerprint(&self) -> String {
+        String::new()
+    }
+}
+
+/// flattens segments into the plain text the summarizers work on
+pub fn transcript_text(segments: &[TranscriptSegment]) -> String {
+    segments.iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// caption track as listed in the watch page player response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptionTrack {
+    base_url: String,
+    language_code: String,
+    // "asr" for auto-generated captions, absent for manual ones
+    kind: Option<String>,
+    // {"simpleText": ...} or {"runs": [{"text": ...}]}
+    name: Option<Value>,
+    // youtube can machine translate the track with &tlang=
+    #[serde(default)]
+    is_translatable: bool,
+}
+
+impl CaptionTrack {
+    fn is_generated(&self) -> bool {
+        self.kind.as_deref() == Some("asr")
+    }
+
+    fn display_name(&self) -> Option<String> {
+        let name = self.name.as_ref()?;
+        name["simpleText"].as_str()
+            .or_else(|| name["runs"][0]["text"].as_str())
+            .map(str::to_string)
+    }
+
+    fn describe(&self) -> String {
+        if self.is_generated() {
+            format!("{} (auto-generated)", self.language_code)
+        } else {
+            self.language_code.clone()
+        }
+    }
+
+    fn info(&self) -> TranscriptTrack {
+        TranscriptTrack {
+            language: self.language_code.clone(),
+            name: self.display_name(),
+            generated: self.is_generated(),
+            translated_to: None,
+        }
+    }
+}
+
+// json3 timedtext format
+#[derive(Debug, Deserialize)]
+struct Json3 {
+    #[serde(default)]
+    events: Vec<Json3Event>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Json3Event {
+    #[serde(default)]
+    t_start_ms: u64,
+    #[serde(default)]
+    d_duration_ms: u64,
+    #[serde(default)]
+    segs: Vec<Json3Seg>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Json3Seg {
+    #[serde(default)]
+    utf8: String,
+}
+
+/// fetches captions straight from youtube: watch page -> caption tracks -> timedtext
+pub struct YouTubeTranscriptSource {
+    agent: ureq::Agent,
+    base_url: String,
+    // preferred caption languages, most wanted first; "*" accepts any language
+    languages: Vec<String>,
+    // have youtube translate the captions into this language
+    translate_to: Option<String>,
+}
+
+impl YouTubeTranscriptSource {
+    /// fetches english captions from youtube.com
+    pub fn new() -> Self {
+        Self::with_base_url(YOUTUBE_BASE_URL)
+    }
+
+    /// point the client at another host, e.g. a local server replaying recorded pages
+    pub fn with_base_url(base_url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        YouTubeTranscriptSource {
+            agent,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            languages: vec!["en".to_string()],
+            translate_to: None,
+        }
+    }
+
+    /// languages to try in order, e.g. ["de", "es", "en"]. an empty list keeps english
+    pub fn with_languages(mut self, languages: Vec<String>) -> Self {
+        let languages: Vec<String> = languages.into_iter()
+            .map(|language| language.trim().to_string())
+            .filter(|language| !language.is_empty())
+            .collect();
+        if !languages.is_empty() {
+            self.languages = languages;
+        }
+        self
+    }
+
+    /// fetch youtube's machine translation of tracks in other languages
+    pub fn with_translation(mut self, translate_to: Option<String>) -> Self {
+        self.translate_to = translate_to;
+        self
+    }
+
+    // the first preferred language that has a track wins; within a language,
+    // uploaded captions beat auto-generated ones, and an exact code ("de") beats
+    // a regional one ("de-AT")
+    fn select_track<'a>(&self, tracks: &'a [CaptionTrack]) -> Option<&'a CaptionTrack> {
+        tracks.iter()
+            .filter_map(|track| {
+                let code = track.language_code.to_lowercase();
+                self.languages.iter()
+                    .position(|language| {
+                        let language = language.to_lowercase();
+                        language == "*" || code == language || code.starts_with(&format!("{}-", language))
+                    })
+                    .map(|rank| {
+                        let exact = self.languages[rank].eq_ignore_ascii_case(&track.language_code);
+                        ((rank, track.is_generated(), !exact), track)
+                    })
+            })
+            .min_by_key(|(key, _)| *key)
+            .map(|(_, track)| track)
+    }
+
+    fn get(&self, url: &str) -> Result<String> {
+        let response = self.agent.get(url)
+            .set("Accept-Language", "en-US,en;q=0.9")
+            // skip the EU cookie consent interstitial
+            .set("Cookie", "CONSENT=YES+cb")
+            .call();
+
+        if let Err(ureq::Error::Status(429, _)) = response {
+            return Err(Error::RateLimited {
+                message: format!("YouTube refused {} with status code 429", url),
+                retry_after: None,
+            }.into());
+        }
+
+        response
+            .context(format!("Failed to fetch {}", url))?
+            .into_string()
+            .context(format!("Failed to read response body from {}", url))
+    }
+
+    fn player_response(&self, video_id: &str) -> Result<Value> {
+        let page = self.get(&format!("{}/watch?v={}", self.base_url, video_id))?;
+        extract_page_json(&page, "ytInitialPlayerResponse")
+            .context(format!("Failed to read the watch page of {}", video_id))
+    }
+
+    fn caption_tracks(video_id: &str, player_response: &Value) -> Result<Vec<CaptionTrack>> {
+        let status = player_response["playabilityStatus"]["status"].as_str().unwrap_or("OK");
+        if status != "OK" {
+            let reason = player_response["playabilityStatus"]["reason"]
+                .as_str()
+                .unwrap_or("no reason given");
+
+            // "Sign in to confirm your age", or the dedicated statuses
+            let age_check = status.starts_with("AGE_") || reason.to_lowercase().contains("confirm your age");
+            if age_check {
+                return Err(Error::AgeRestricted { video_id: video_id.to_string() }.into());
+            }
+            return Err(Error::TranscriptUnavailable {
+                video_id: video_id.to_string(),
+                reason: format!("video is not playable ({}): {}", status, reason),
+            }.into());
+        }
+
+        let tracks = &player_response["captions"]["playerCaptionsTracklistRenderer"]["captionTracks"];
+        if tracks.is_null() {
+            return Err(Error::TranscriptsDisabled { video_id: video_id.to_string() }.into());
+        }
+
+        serde_json::from_value(tracks.clone())
+            .map_err(|e| Error::ResponseParse(format!("caption track list: {}", e)).into())
+    }
+
+    // url of the timedtext document, rebased onto our host so recorded fixtures work locally
+    fn track_url(&self, track: &CaptionTrack) -> String {
+        let url = match track.base_url.strip_prefix(YOUTUBE_BASE_URL) {
+            Some(path) => format!("{}{}", self.base_url, path),
+            None if track.base_url.starts_with('/') => format!("{}{}", self.base_url, track.base_url),
+            None => track.base_url.clone(),
+        };
+        format!("{}&fmt=json3", url)
+    }
+
+    // target language when the track should be fetched translated
+    fn translation_for(&self, track: &CaptionTrack) -> Option<String> {
+        let target = self.translate_to.as_deref()?;
+        if translator::is_same_language(&track.language_code, target) {
+            return None;
+        }
+        if !track.is_translatable {
+            warn!(
+                "YouTube can't translate the {} transcript, summarizing it untranslated",
+                track.describe()
+            );
+            return None;
+        }
+        Some(target.to_string())
+    }
+}
+
+impl Default for YouTubeTranscriptSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranscriptSource for YouTubeTranscriptSource {
+    fn fetch_transcript(&self, video_id: &str) -> Result<Transcript> {
+        info!("Fetching transcript for video ID: {}", video_id);
+
+        let player_response = self.player_response(video_id)?;
+        let tracks = Self::caption_tracks(video_id, &player_response)?;
+
+        let track = self.select_track(&tracks).ok_or_else(|| {
+            let available: Vec<String> = tracks.iter().map(CaptionTrack::describe).collect();
+            Error::TranscriptUnavailable {
+                video_id: video_id.to_string(),
+                reason: format!("none in {} (available: {})", self.languages.join(", "), available.join(", ")),
+            }
+        })?;
+        let translation = self.translation_for(track);
+        let mut url = self.track_url(track);
+        match &translation {
+            Some(target) => {
+                info!("Using {} transcript, translated to {} by YouTube", track.describe(), target);
+                url = format!("{}&tlang={}", url, target);
+            }
+            None => info!("Using {} transcript", track.describe()),
+        }
+
+        let body = self.get(&url)?;
+        let segments = parse_timedtext(&body)
+            .context(format!("Failed to read the {} transcript of {}", track.describe(), video_id))?;
+
+        if segments.is_empty() {
+            return Err(Error::TranscriptUnavailable {
+                video_id: video_id.to_string(),
+                reason: "the transcript is empty".to_string(),
+            }.into());
+        }
+
+        let description = player_response["videoDetails"]["shortDescription"]
+            .as_str()
+            .unwrap_or_default();
+
+        Ok(Transcript {
+            segments,
+            chapters: chapters::parse_chapter_markers(description),
+            track: Some(TranscriptTrack {
+                translated_to: translation,
+                ..track.info()
+            }),
+        })
+    }
+
+    fn fingerprint(&self) -> String {
+        match &self.translate_to {
+            Some(target) => format!("{}>{}", self.languages.join(","), target),
+            None => self.languages.join(","),
+        }
+    }
+}
+
+/// pulls a json object assigned in an inline script, e.g. `ytInitialPlayerResponse`,
+/// out of a youtube page
+pub fn extract_page_json(page: &str, variable: &str) -> Result<Value> {
+    let marker = format!("{} = ", variable);
+    let start = page.find(&marker)
+        .map(|i| i + marker.len())
+        .ok_or_else(|| Error::ResponseParse(format!("could not find {} in the page", variable)))?;
+
+    // the object is followed by more script, so only read the first json value
+    serde_json::Deserializer::from_str(&page[start..])
+        .into_iter::<Value>()
+        .next()
+        .ok_or_else(|| Error::ResponseParse(format!("{} in the page is empty", variable)))?
+        .map_err(|e| Error::ResponseParse(format!("{} in the page: {}", variable, e)).into())
+}
+
+// parses a timedtext document in either json3 or xml (srv1/srv3) format into segments
+fn parse_timedtext(body: &str) -> Result<Vec<TranscriptSegment>> {
+    let body = body.trim_start();
+
+    if body.starts_with('{') {
+        let doc: Json3 = serde_json::from_str(body)
+            .map_err(|e| Error::ResponseParse(format!("json3 transcript: {}", e)))?;
+
+        return Ok(doc.events.iter()
+            .map(|e| TranscriptSegment {
+                start: e.t_start_ms as f64 / 1000.0,
+                duration: e.d_duration_ms as f64 / 1000.0,
+                text: normalize_whitespace(&e.segs.iter().map(|s| s.utf8.as_str()).collect::<String>()),
+            })
+            .filter(|segment| !segment.text.is_empty())
+            .collect());
+    }
+
+    if body.starts_with('<') {
+        // srv1 uses <text start dur> in seconds, srv3 uses <p t d> in milliseconds
+        // with optional <s> word spans
+        static ELEMENT: OnceLock<Regex> = OnceLock::new();
+        static TAG: OnceLock<Regex> = OnceLock::new();
+        let element = ELEMENT.get_or_init(|| Regex::new(r"(?s)<(text|p)\b([^>]*)>(.*?)</(?:text|p)>").unwrap());
+        let tags = TAG.get_or_init(|| Regex::new(r"<[^>]+>").unwrap());
+
+        return Ok(element.captures_iter(body)
+            .map(|cap| {
+                let (start, duration) = if &cap[1] == "text" {
+                    (xml_attr(&cap[2], "start"), xml_attr(&cap[2], "dur"))
+                } else {
+                    (xml_attr(&cap[2], "t") / 1000.0, xml_attr(&cap[2], "d") / 1000.0)
+                };
+                TranscriptSegment {
+                    start,
+                    duration,
+                    text: normalize_whitespace(&unescape_xml(&tags.replace_all(&cap[3], ""))),
+                }
+            })
+            .filter(|segment| !segment.text.is_empty())
+            .collect());
+    }
+
+    Err(Error::ResponseParse("unrecognised transcript format".to_string()).into())
+}
+
+// numeric attribute value, 0 when missing or malformed
+fn xml_attr(attrs: &str, name: &str) -> f64 {
+    static ATTR: OnceLock<Regex> = OnceLock::new();
+    let re = ATTR.get_or_init(|| Regex::new(r#"([\w:-]+)="([^"]*)""#).unwrap());
+    re.captures_iter(attrs)
+        .find(|cap| &cap[1] == name)
+        .and_then(|cap| cap[2].parse().ok())
+        .unwrap_or(0.0)
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// decodes the predefined xml entities and numeric character references
+fn unescape_xml(text: &str) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let re = ENTITY.get_or_init(|| Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|amp|lt|gt|quot|apos);").unwrap());
+    let once = re.replace_all(text, |cap: &regex::Captures| decode_entity(&cap[1]));
+    // captions are frequently double-escaped (&amp;#39;)
+    re.replace_all(&once, |cap: &regex::Captures| decode_entity(&cap[1])).into_owned()
+}
+
+fn decode_entity(entity: &str) -> String {
+    match entity {
+        "amp" => "&".to_string(),
+        "lt" => "<".to_string(),
+        "gt" => ">".to_string(),
+        "quot" => "\"".to_string(),
+        "apos" => "'".to_string(),
+        _ => {
+            let code = match entity.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity[1..].parse().ok(),
+            };
+            code.and_then(char::from_u32)
+                .map(String::from)
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error;
+    use serde_json::json;
+
+    fn track(language_code: &str, kind: Option<&str>) -> CaptionTrack {
+        CaptionTrack {
+            base_url: format!("https://www.youtube.com/api/timedtext?lang={}", language_code),
+            language_code: language_code.to_string(),
+            kind: kind.map(String::from),
+            name: None,
+            is_translatable: true,
+        }
+    }
+
+    fn source(languages: &[&str]) -> YouTubeTranscriptSource {
+        YouTubeTranscriptSource::new().with_languages(languages.iter().map(|l| l.to_string()).collect())
+    }
+
+    fn selected(languages: &[&str], tracks: &[CaptionTrack]) -> Option<String> {
+        source(languages).select_track(tracks).map(CaptionTrack::describe)
+    }
+
+    #[test]
+    fn extracts_json_assigned_in_page() {
+        let page = r#"<script>var ytInitialPlayerResponse = {"a": {"b": "};"}, "c": [1, 2]};var meta = {};</script>"#;
+        let value = extract_page_json(page, "ytInitialPlayerResponse").unwrap();
+        assert_eq!(value, json!({"a": {"b": "};"}, "c": [1, 2]}));
+    }
+
+    #[test]
+    fn missing_or_broken_page_json_is_an_error() {
+        for page in ["<html></html>", "var ytInitialPlayerResponse = ", "var ytInitialPlayerResponse = {\"a\": "] {
+            let error = extract_page_json(page, "ytInitialPlayerResponse").unwrap_err();
+            assert!(matches!(error::classify(&error), Some(Error::ResponseParse(_))), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn parses_json3() {
+        let body = r#"{"events": [
+            {"tStartMs": 0, "dDurationMs": 1500, "segs": [{"utf8": "hello "}, {"utf8": "world"}]},
+            {"tStartMs": 1500, "dDurationMs": 500, "segs": [{"utf8": "\n"}]},
+            {"tStartMs": 2000},
+            {"tStartMs": 2500, "dDurationMs": 1000, "segs": [{"utf8": "second\nline"}]}
+        ]}"#;
+
+        assert_eq!(parse_timedtext(body).unwrap(), vec![
+            TranscriptSegment { start: 0.0, duration: 1.5, text: "hello world".to_string() },
+            TranscriptSegment { start: 2.5, duration: 1.0, text: "second line".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn parses_srv1_xml() {
+        let body = r#"<?xml version="1.0" encoding="utf-8" ?><transcript>
+            <text start="0.5" dur="2.25">Tom &amp; Jerry</text>
+            <text start="3" dur="1">it&amp;#39;s &lt;fine&gt;</text>
+            <text start="4" dur="1">   </text>
+        </transcript>"#;
+
+        assert_eq!(parse_timedtext(body).unwrap(), vec![
+            TranscriptSegment { start: 0.5, duration: 2.25, text: "Tom & Jerry".to_string() },
+            TranscriptSegment { start: 3.0, duration: 1.0, text: "it's <fine>".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn parses_srv3_xml() {
+        let body = r#"<timedtext format="3"><body>
+            <p t="1000" d="2500" w="1"><s ac="0">one</s><s t="500"> two</s></p>
+            <p t="4000" d="1000">caf&#233; &#x263A; &quot;q&quot; &apos;a&apos;</p>
+        </body></timedtext>"#;
+
+        assert_eq!(parse_timedtext(body).unwrap(), vec![
+            TranscriptSegment { start: 1.0, duration: 2.5, text: "one two".to_string() },
+            TranscriptSegment { start: 4.0, duration: 1.0, text: "café ☺ \"q\" 'a'".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn missing_xml_attributes_default_to_zero() {
+        assert_eq!(xml_attr(r#"start="1.5" dur="x""#, "start"), 1.5);
+        assert_eq!(xml_attr(r#"start="1.5" dur="x""#, "dur"), 0.0);
+        assert_eq!(xml_attr(r#"restart="9""#, "start"), 0.0);
+    }
+
+    #[test]
+    fn unknown_timedtext_format_is_an_error() {
+        for body in ["WEBVTT\n\n00:00.000 --> 00:01.000\nhi", "{not json"] {
+            let error = parse_timedtext(body).unwrap_err();
+            assert!(matches!(error::classify(&error), Some(Error::ResponseParse(_))), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn unescapes_double_escaped_entities_once_more_only() {
+        assert_eq!(unescape_xml("&amp;#39;"), "'");
+        assert_eq!(unescape_xml("&amp;amp;"), "&");
+        assert_eq!(unescape_xml("&amp;amp;amp;"), "&amp;");
+        assert_eq!(unescape_xml("&#99999999;"), "");
+        assert_eq!(unescape_xml("a & b"), "a & b");
+    }
+
+    #[test]
+    fn selects_tracks_by_preference() {
+        let tracks = [
+            track("en", Some("asr")),
+            track("de", Some("asr")),
+            track("de-AT", None),
+            track("es", None),
+            track("en-GB", None),
+        ];
+
+        // language order wins over everything else
+        assert_eq!(selected(&["es", "de"], &tracks).as_deref(), Some("es"));
+        // uploaded captions beat auto-generated ones, even regional ones
+        assert_eq!(selected(&["de"], &tracks).as_deref(), Some("de-AT"));
+        assert_eq!(selected(&["en"], &tracks).as_deref(), Some("en-GB"));
+        // an exact code beats a regional one of the same kind
+        assert_eq!(selected(&["de-at"], &tracks).as_deref(), Some("de-AT"));
+        // * takes anything, preferring uploaded captions
+        assert_eq!(selected(&["fr", "*"], &tracks).as_deref(), Some("de-AT"));
+        assert_eq!(selected(&["fr"], &tracks), None);
+        assert_eq!(selected(&["en"], &[]), None);
+    }
+
+    #[test]
+    fn exact_code_beats_regional_one() {
+        let tracks = [track("pt-BR", None), track("pt", None)];
+        assert_eq!(selected(&["pt"], &tracks).as_deref(), Some("pt"));
+    }
+
+    #[test]
+    fn classifies_unplayable_videos() {
+        let age = json!({"playabilityStatus": {"status": "LOGIN_REQUIRED", "reason": "Sign in to confirm your age"}});
+        let private = json!({"playabilityStatus": {"status": "ERROR", "reason": "Video unavailable"}});
+        let no_captions = json!({"playabilityStatus": {"status": "OK"}});
+
+        let kind = |response: &Value| {
+            let error = YouTubeTranscriptSource::caption_tracks("dQw4w9WgXcQ", response).unwrap_err();
+            crate::error::classify(&error).map(Error::kind)
+        };
+        assert_eq!(kind(&age), Some("age_restricted"));
+        assert_eq!(kind(&private), Some("transcript_unavailable"));
+        assert_eq!(kind(&no_captions), Some("transcripts_disabled"));
+    }
+}
@@ -0,0 +1,30 @@
+//! The interface translation backends implement.
+
+use anyhow::Result;
+
+/// a backend that translates a chunk of transcript text into one target language
+pub trait Translator: Send + Sync {
+    /// short identifier of the backend and model, e.g. "huggingface:Helsinki-NLP/opus-mt-mul-en"
+    fn name(&self) -> &str;
+
+    /// language code translations are produced in, e.g. "en"
+    fn target_language(&self) -> &str;
+
+    /// longest chunk (in model tokens) the backend accepts in one request
+    fn max_input_tokens(&self) -> usize;
+
+    /// translates `chunk`, which is at most `max_input_tokens()` long, into
+    /// `target_language()`
+    fn translate_chunk(&self, chunk: &str) -> Result<String>;
+
+    /// everything that affects the output for a given chunk, used as part of cache keys
+    fn fingerprint(&self) -> String {
+        format!("{}|{}|{}", self.name(), self.target_language(), self.max_input_tokens())
+    }
+}
+
+/// whether text in `language` already is in `target`, e.g. "en-GB" for "en"
+pub fn is_same_language(language: &str, target: &str) -> bool {
+    let base = |code: &str| code.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+    base(language) == base(target)
+}
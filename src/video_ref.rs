@@ -0,0 +1,263 @@
+//! Getting the video out of the many forms of YouTube link.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::str::FromStr;
+use url::Url;
+
+use crate::error::Error;
+
+// hosts that serve the regular watch/shorts/embed paths
+const YOUTUBE_HOSTS: &[&str] = &[
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "gaming.youtube.com",
+    "youtube-nocookie.com",
+    "www.youtube-nocookie.com",
+];
+
+// path prefixes that are followed directly by the video id
+const ID_PATHS: &[&str] = &["shorts", "live", "embed", "v", "e"];
+
+/// a video as referenced by a link: which video, where to start, and the
+/// playlist it was opened from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VideoRef {
+    /// the 11 character youtube id
+    pub id: String,
+    /// seconds, from t= or start=
+    pub start_time: Option<u64>,
+    /// from list=, when the link was opened from a playlist
+    pub playlist_id: Option<String>,
+}
+
+impl VideoRef {
+    /// accepts a bare 11 character id or any youtube / youtu.be link, with or without scheme
+    pub fn parse(input: &str) -> Result<VideoRef> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(Error::InvalidUrl("Empty YouTube URL".to_string()).into());
+        }
+
+        if is_video_id(input) {
+            return Ok(VideoRef {
+                id: input.to_string(),
+                start_time: None,
+                playlist_id: None,
+            });
+        }
+
+        let with_scheme = if input.contains("://") {
+            input.to_string()
+        } else {
+            format!("https://{}", input)
+        };
+        let url = Url::parse(&with_scheme)
+            .map_err(|e| Error::InvalidUrl(format!("'{}' is not a valid URL: {}", input, e)))?;
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::InvalidUrl(format!("'{}' is not a YouTube URL", input)).into());
+        }
+
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let mut segments = url.path_segments()
+            .map(|s| s.filter(|p| !p.is_empty()).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter();
+
+        let id = if host == "youtu.be" || host == "www.youtu.be" {
+            segments.next().map(String::from)
+        } else if YOUTUBE_HOSTS.contains(&host.as_str()) {
+            match segments.next() {
+                Some("watch") => query_param(&url, "v"),
+                Some(prefix) if ID_PATHS.contains(&prefix) => segments.next().map(String::from),
+                Some("playlist") => {
+                    return Err(Error::InvalidUrl(format!("'{}' is a playlist, not a single video; use the batch command", input)).into());
+                }
+                _ => None,
+            }
+        } else {
+            return Err(Error::InvalidUrl(format!("'{}' is not a YouTube URL", input)).into());
+        };
+
+        let id = id.ok_or_else(|| Error::InvalidUrl(format!("No video ID found in '{}'", input)))?;
+        if !is_video_id(&id) {
+            return Err(Error::InvalidUrl(format!("'{}' is not a valid YouTube video ID", id)).into());
+        }
+
+        // t= on watch and youtu.be links, start= on embeds, #t= in older share links
+        let start_time = query_param(&url, "t")
+            .or_else(|| query_param(&url, "start"))
+            .or_else(|| url.fragment().and_then(|f| f.strip_prefix("t=")).map(String::from))
+            .and_then(|t| parse_time_offset(&t));
+
+        Ok(VideoRef {
+            id,
+            start_time,
+            playlist_id: query_param(&url, "list").filter(|list| !list.is_empty()),
+        })
+    }
+}
+
+impl FromStr for VideoRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        VideoRef::parse(s)
+    }
+}
+
+fn is_video_id(candidate: &str) -> bool {
+    let re = Regex::new(r"^[0-9A-Za-z_-]{11}$").unwrap();
+    re.is_match(candidate)
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+// "90", "90s", "1m30s" and "1h2m3s" are all used for the same thing
+fn parse_time_offset(value: &str) -> Option<u64> {
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+
+    let re = Regex::new(r"^(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s)?$").unwrap();
+    let cap = re.captures(value)?;
+    if cap.get(1).is_none() && cap.get(2).is_none() && cap.get(3).is_none() {
+        return None;
+    }
+
+    // offsets too large for a u64 are refused rather than wrapped
+    let part = |i: usize| -> Option<u64> {
+        cap.get(i).map_or(Some(0), |m| m.as_str().parse().ok())
+    };
+    part(1)?.checked_mul(3600)?
+        .checked_add(part(2)?.checked_mul(60)?)?
+        .checked_add(part(3)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "dQw4w9WgXcQ";
+
+    #[test]
+    fn parses_every_link_form() {
+        // input, start time, playlist
+        let cases: &[(&str, Option<u64>, Option<&str>)] = &[
+            ("dQw4w9WgXcQ", None, None),
+            ("  dQw4w9WgXcQ\n", None, None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ", None, None),
+            ("http://youtube.com/watch?v=dQw4w9WgXcQ", None, None),
+            ("www.youtube.com/watch?v=dQw4w9WgXcQ", None, None),
+            ("https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ", None, None),
+            ("https://youtu.be/dQw4w9WgXcQ", None, None),
+            ("youtu.be/dQw4w9WgXcQ?si=abc", None, None),
+            ("https://www.youtube.com/shorts/dQw4w9WgXcQ", None, None),
+            ("https://www.youtube.com/live/dQw4w9WgXcQ?feature=share", None, None),
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ", None, None),
+            ("https://www.youtube.com/v/dQw4w9WgXcQ", None, None),
+            ("https://m.youtube.com/watch?v=dQw4w9WgXcQ", None, None),
+            ("https://music.youtube.com/watch?v=dQw4w9WgXcQ", None, None),
+            ("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ", None, None),
+            ("https://YOUTUBE.com/watch?v=dQw4w9WgXcQ", None, None),
+            // start times
+            ("https://youtu.be/dQw4w9WgXcQ?t=90", Some(90), None),
+            ("https://youtu.be/dQw4w9WgXcQ?t=90s", Some(90), None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1m30s", Some(90), None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1h2m3s", Some(3723), None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=2h", Some(7200), None),
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ?start=42", Some(42), None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ#t=15", Some(15), None),
+            ("https://youtu.be/dQw4w9WgXcQ?t=soon", None, None),
+            // playlists
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123abc", None, Some("PL123abc")),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123abc&t=5", Some(5), Some("PL123abc")),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=", None, None),
+        ];
+
+        for (input, start_time, playlist_id) in cases {
+            let video = VideoRef::parse(input).unwrap_or_else(|e| panic!("{:?}: {:#}", input, e));
+            assert_eq!(
+                video,
+                VideoRef {
+                    id: ID.to_string(),
+                    start_time: *start_time,
+                    playlist_id: playlist_id.map(String::from),
+                },
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_single_video() {
+        let cases = [
+            "",
+            "   ",
+            "dQw4w9WgXc",
+            "dQw4w9WgXcQQ",
+            "https://example.com/watch?v=dQw4w9WgXcQ",
+            "https://example.com/dQw4w9WgXcQ",
+            "https://notyoutube.com/embed/dQw4w9WgXcQ",
+            "ftp://youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://www.youtube.com/playlist?list=PL123abc",
+            "https://www.youtube.com/watch?list=PL123abc",
+            "https://www.youtube.com/",
+            "https://www.youtube.com/@channel",
+            "https://youtu.be/",
+            "https://youtu.be/short",
+            "https://www.youtube.com/watch?v=bad id here",
+        ];
+
+        for input in cases {
+            let error = VideoRef::parse(input).expect_err(input);
+            assert!(
+                matches!(crate::error::classify(&error), Some(Error::InvalidUrl(_))),
+                "{:?}: {:#}",
+                input,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn playlist_links_point_to_batch() {
+        let error = VideoRef::parse("https://www.youtube.com/playlist?list=PL123abc").unwrap_err();
+        assert!(error.to_string().contains("batch"), "{}", error);
+    }
+
+    #[test]
+    fn time_offsets() {
+        let cases = [
+            ("0", Some(0)),
+            ("75", Some(75)),
+            ("75s", Some(75)),
+            ("3m", Some(180)),
+            ("1h", Some(3600)),
+            ("1h0m5s", Some(3605)),
+            ("", None),
+            ("1x", None),
+            ("m", None),
+            ("9999999999999999h", None),
+            ("307445734561825861m", None),
+            ("99999999999999999999s", None),
+            ("5124095576030431h16s", None),
+            ("5124095576030431h15s", Some(u64::MAX)),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_time_offset(value), expected, "{:?}", value);
+        }
+
+        let video = VideoRef::parse("https://youtu.be/dQw4w9WgXcQ?t=9999999999999999h").unwrap();
+        assert_eq!(video.start_time, None);
+    }
+}
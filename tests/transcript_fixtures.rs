@@ -0,0 +1,153 @@
+// runs the transcript fetcher against recorded youtube pages served from
+// tests/fixtures by a local http server
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use youtube_summarizer::error::{self, Error};
+use youtube_summarizer::{TranscriptSource, YouTubeTranscriptSource};
+
+// serves /watch?v=<id> from watch_<id>.html and /api/<name>?... from <name>.json
+// or <name>.xml; returns the base url and the request targets seen so far
+fn fixture_server() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&requests);
+
+    thread::spawn(move || {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let target = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+            // drain the headers
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap_or(0) > 2 {
+                header.clear();
+            }
+            seen.lock().unwrap().push(target.clone());
+
+            let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+            let file = match path {
+                "/watch" => query.split('&')
+                    .find_map(|pair| pair.strip_prefix("v="))
+                    .map(|id| fixtures.join(format!("watch_{}.html", id))),
+                _ => path.strip_prefix("/api/").and_then(|name| {
+                    ["json", "xml"].iter()
+                        .map(|ext| fixtures.join(format!("{}.{}", name, ext)))
+                        .find(|file| file.exists())
+                }),
+            };
+
+            let response = match file.and_then(|file| std::fs::read(file).ok()) {
+                Some(body) => {
+                    let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).into_bytes();
+                    response.extend(body);
+                    response
+                }
+                None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+            };
+            let _ = stream.write_all(&response);
+        }
+    });
+
+    (base_url, requests)
+}
+
+fn languages(codes: &[&str]) -> Vec<String> {
+    codes.iter().map(|code| code.to_string()).collect()
+}
+
+#[test]
+fn fetches_the_preferred_track_with_chapters() {
+    let (base_url, requests) = fixture_server();
+    let transcript = YouTubeTranscriptSource::with_base_url(&base_url)
+        .fetch_transcript("dQw4w9WgXcQ")
+        .unwrap();
+
+    let texts: Vec<&str> = transcript.segments.iter().map(|s| s.text.as_str()).collect();
+    assert_eq!(texts, ["Welcome to a short tour of Rust.", "Every value has a single owner.", "Traits define shared behaviour."]);
+    assert_eq!(transcript.segments[1].start, 5.0);
+    assert_eq!(transcript.segments[1].duration, 3.0);
+
+    let chapters: Vec<(f64, &str)> = transcript.chapters.iter().map(|c| (c.start, c.title.as_str())).collect();
+    assert_eq!(chapters, [(0.0, "Intro"), (5.0, "Ownership"), (62.0, "Traits")]);
+
+    let track = transcript.track.unwrap();
+    assert_eq!((track.language.as_str(), track.name.as_deref(), track.generated), ("en", Some("English"), false));
+    assert_eq!(track.translated_to, None);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0], "/watch?v=dQw4w9WgXcQ");
+    assert!(requests[1].starts_with("/api/timedtext?") && requests[1].contains("fmt=json3"), "{}", requests[1]);
+}
+
+#[test]
+fn falls_back_through_the_language_list() {
+    let (base_url, _) = fixture_server();
+    let transcript = YouTubeTranscriptSource::with_base_url(&base_url)
+        .with_languages(languages(&["fr", "de", "en"]))
+        .fetch_transcript("dQw4w9WgXcQ")
+        .unwrap();
+
+    let track = transcript.track.unwrap();
+    assert_eq!((track.language.as_str(), track.generated), ("de", true));
+    assert_eq!(transcript.segments[0].text, "willkommen zu rust");
+    assert_eq!(transcript.segments[2].text, "traits & generics");
+    assert_eq!(transcript.segments[2].start, 62.0);
+}
+
+#[test]
+fn asks_youtube_for_a_translation() {
+    let (base_url, requests) = fixture_server();
+    let transcript = YouTubeTranscriptSource::with_base_url(&base_url)
+        .with_languages(languages(&["de"]))
+        .with_translation(Some("en".to_string()))
+        .fetch_transcript("dQw4w9WgXcQ")
+        .unwrap();
+
+    assert_eq!(transcript.track.unwrap().translated_to.as_deref(), Some("en"));
+    assert!(requests.lock().unwrap()[1].contains("&tlang=en"));
+}
+
+#[test]
+fn untranslatable_tracks_are_used_as_they_are() {
+    let (base_url, requests) = fixture_server();
+    let transcript = YouTubeTranscriptSource::with_base_url(&base_url)
+        .with_languages(languages(&["es"]))
+        .with_translation(Some("en".to_string()))
+        .fetch_transcript("dQw4w9WgXcQ")
+        .unwrap();
+
+    assert_eq!(transcript.track.unwrap().translated_to, None);
+    assert_eq!(transcript.segments[1].text, "Cada valor tiene un dueño.");
+    assert!(!requests.lock().unwrap()[1].contains("tlang"));
+}
+
+#[test]
+fn missing_language_lists_available_tracks() {
+    let (base_url, _) = fixture_server();
+    let error = YouTubeTranscriptSource::with_base_url(&base_url)
+        .with_languages(languages(&["ja"]))
+        .fetch_transcript("dQw4w9WgXcQ")
+        .unwrap_err();
+
+    assert!(matches!(error::classify(&error), Some(Error::TranscriptUnavailable { .. })), "{:#}", error);
+    assert!(format!("{:#}", error).contains("en, de (auto-generated), es"), "{:#}", error);
+}
+
+#[test]
+fn video_without_captions() {
+    let (base_url, _) = fixture_server();
+    let error = YouTubeTranscriptSource::with_base_url(&base_url)
+        .fetch_transcript("aaaaaaaaaaa")
+        .unwrap_err();
+
+    assert!(matches!(error::classify(&error), Some(Error::TranscriptsDisabled { .. })), "{:#}", error);
+}